@@ -0,0 +1,15 @@
+CREATE TYPE review_type AS ENUM ('user', 'server');
+
+CREATE TABLE reviews (
+    id SERIAL PRIMARY KEY,
+    target_id BIGINT NOT NULL,
+    reviewer_id BIGINT NOT NULL,
+    rating INTEGER NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    comment TEXT,
+    review_type review_type NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT reviews_target_reviewer_type_key UNIQUE (target_id, reviewer_id, review_type)
+);
+
+CREATE INDEX reviews_target_type_created_idx ON reviews (target_id, review_type, created_at DESC);
+CREATE INDEX reviews_reviewer_idx ON reviews (reviewer_id);
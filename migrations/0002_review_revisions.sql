@@ -0,0 +1,28 @@
+ALTER TABLE reviews ADD COLUMN updated_at TIMESTAMPTZ;
+
+CREATE TABLE review_revisions (
+    id SERIAL PRIMARY KEY,
+    review_id INTEGER NOT NULL REFERENCES reviews (id) ON DELETE CASCADE,
+    rating INTEGER NOT NULL,
+    comment TEXT,
+    written_at TIMESTAMPTZ,
+    replaced_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX review_revisions_review_idx ON review_revisions (review_id, replaced_at DESC);
+
+-- Keeps the previous text of a review whenever its rating or comment changes,
+-- no matter which code path performed the update.
+CREATE FUNCTION record_review_revision() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO review_revisions (review_id, rating, comment, written_at)
+    VALUES (OLD.id, OLD.rating, OLD.comment, COALESCE(OLD.updated_at, OLD.created_at));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reviews_record_revision
+    AFTER UPDATE OF rating, comment ON reviews
+    FOR EACH ROW
+    WHEN (OLD.rating IS DISTINCT FROM NEW.rating OR OLD.comment IS DISTINCT FROM NEW.comment)
+    EXECUTE FUNCTION record_review_revision();
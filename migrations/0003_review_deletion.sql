@@ -0,0 +1,7 @@
+ALTER TABLE reviews
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by BIGINT,
+    ADD COLUMN delete_reason TEXT;
+
+CREATE INDEX reviews_live_target_idx ON reviews (target_id, review_type, created_at DESC)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,19 @@
+CREATE TYPE review_status AS ENUM ('published', 'hidden');
+
+ALTER TABLE reviews ADD COLUMN status review_status NOT NULL DEFAULT 'published';
+
+CREATE TYPE report_resolution AS ENUM ('dismissed', 'hidden', 'deleted');
+
+CREATE TABLE review_reports (
+    id SERIAL PRIMARY KEY,
+    review_id INTEGER NOT NULL REFERENCES reviews (id) ON DELETE CASCADE,
+    reporter_id BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    resolution report_resolution,
+    resolved_by BIGINT,
+    resolved_at TIMESTAMPTZ,
+    CONSTRAINT review_reports_review_reporter_key UNIQUE (review_id, reporter_id)
+);
+
+CREATE INDEX review_reports_open_idx ON review_reports (review_id) WHERE resolution IS NULL;
@@ -0,0 +1,12 @@
+CREATE TYPE blacklist_scope AS ENUM ('reviewing', 'reviewed', 'everywhere');
+
+CREATE TABLE blacklist (
+    id SERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    scope blacklist_scope NOT NULL,
+    reason TEXT,
+    created_by BIGINT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ,
+    CONSTRAINT blacklist_user_scope_key UNIQUE (user_id, scope)
+);
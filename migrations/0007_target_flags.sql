@@ -0,0 +1,12 @@
+CREATE TABLE target_flags (
+    id SERIAL PRIMARY KEY,
+    target_id BIGINT NOT NULL,
+    review_type review_type NOT NULL,
+    reason TEXT NOT NULL,
+    frozen_average DOUBLE PRECISION,
+    flagged_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL,
+    cleared_by BIGINT,
+    cleared_at TIMESTAMPTZ,
+    CONSTRAINT target_flags_target_type_key UNIQUE (target_id, review_type)
+);
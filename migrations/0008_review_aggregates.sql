@@ -0,0 +1,33 @@
+CREATE TABLE review_aggregates (
+    target_id BIGINT NOT NULL,
+    review_type review_type NOT NULL,
+    review_count BIGINT NOT NULL DEFAULT 0,
+    rating_sum BIGINT NOT NULL DEFAULT 0,
+    stars_1 BIGINT NOT NULL DEFAULT 0,
+    stars_2 BIGINT NOT NULL DEFAULT 0,
+    stars_3 BIGINT NOT NULL DEFAULT 0,
+    stars_4 BIGINT NOT NULL DEFAULT 0,
+    stars_5 BIGINT NOT NULL DEFAULT 0,
+    last_review_at TIMESTAMPTZ,
+    PRIMARY KEY (target_id, review_type)
+);
+
+INSERT INTO review_aggregates (
+    target_id, review_type, review_count, rating_sum,
+    stars_1, stars_2, stars_3, stars_4, stars_5, last_review_at
+)
+SELECT target_id, review_type, COUNT(*), SUM(rating),
+    COUNT(*) FILTER (WHERE rating = 1),
+    COUNT(*) FILTER (WHERE rating = 2),
+    COUNT(*) FILTER (WHERE rating = 3),
+    COUNT(*) FILTER (WHERE rating = 4),
+    COUNT(*) FILTER (WHERE rating = 5),
+    MAX(created_at)
+FROM reviews
+WHERE deleted_at IS NULL AND status = 'published'
+    AND NOT EXISTS (
+        SELECT 1 FROM blacklist
+        WHERE blacklist.user_id = reviews.reviewer_id AND blacklist.scope = 'everywhere'
+            AND (blacklist.expires_at IS NULL OR blacklist.expires_at > CURRENT_TIMESTAMP)
+    )
+GROUP BY target_id, review_type;
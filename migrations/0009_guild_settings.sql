@@ -0,0 +1,8 @@
+CREATE TYPE score_view AS ENUM ('all_time', 'recent', 'decayed');
+
+CREATE TABLE guild_settings (
+    guild_id BIGINT PRIMARY KEY,
+    score_view score_view NOT NULL DEFAULT 'all_time',
+    updated_by BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,31 @@
+-- Aspects a review can rate next to its overall rating. Rows without a guild are the
+-- defaults, a guild with rows of its own for a review type uses only those.
+CREATE TABLE rating_criteria (
+    id SERIAL PRIMARY KEY,
+    review_type review_type NOT NULL,
+    guild_id BIGINT,
+    key TEXT NOT NULL,
+    label TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE UNIQUE INDEX rating_criteria_scope_key_idx ON rating_criteria (review_type, COALESCE(guild_id, 0), key);
+
+INSERT INTO rating_criteria (review_type, key, label, position) VALUES
+    ('server', 'moderation', 'Moderation', 0),
+    ('server', 'activity', 'Activity', 1),
+    ('server', 'friendliness', 'Friendliness', 2),
+    ('user', 'trustworthiness', 'Trustworthiness', 0),
+    ('user', 'communication', 'Communication', 1);
+
+-- Criterion key to stars, criteria the reviewer skipped are left out
+ALTER TABLE reviews ADD COLUMN criteria JSONB NOT NULL DEFAULT '{}';
+
+CREATE TABLE criteria_aggregates (
+    target_id BIGINT NOT NULL,
+    review_type review_type NOT NULL,
+    criterion TEXT NOT NULL,
+    rating_count BIGINT NOT NULL DEFAULT 0,
+    rating_sum BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (target_id, review_type, criterion)
+);
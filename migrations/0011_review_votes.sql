@@ -0,0 +1,21 @@
+ALTER TABLE reviews ADD COLUMN helpful_votes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE reviews ADD COLUMN unhelpful_votes INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE review_votes (
+    review_id INTEGER NOT NULL REFERENCES reviews (id) ON DELETE CASCADE,
+    voter_id BIGINT NOT NULL,
+    helpful BOOLEAN NOT NULL,
+    voted_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (review_id, voter_id)
+);
+
+CREATE TABLE review_reactions (
+    review_id INTEGER NOT NULL REFERENCES reviews (id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL,
+    emoji TEXT NOT NULL,
+    reacted_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (review_id, user_id)
+);
+
+CREATE INDEX reviews_target_type_helpful_idx
+    ON reviews (target_id, review_type, (helpful_votes - unhelpful_votes) DESC, created_at DESC);
@@ -0,0 +1,7 @@
+-- One public answer per review from the reviewed user, or the reviewed server's owner or staff
+ALTER TABLE reviews ADD COLUMN reply TEXT;
+ALTER TABLE reviews ADD COLUMN reply_by BIGINT;
+ALTER TABLE reviews ADD COLUMN replied_at TIMESTAMPTZ;
+
+-- Members with this role may answer reviews of the guild next to its owner
+ALTER TABLE guild_settings ADD COLUMN reply_role BIGINT;
@@ -0,0 +1,27 @@
+-- Only a SHA-256 hash of each key is kept, the key itself is shown once when it is issued
+CREATE TABLE api_keys (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL,
+    scopes TEXT[] NOT NULL,
+    created_by BIGINT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    CONSTRAINT api_keys_key_hash_key UNIQUE (key_hash)
+);
+
+-- Which key made each write through the API
+CREATE TABLE api_key_writes (
+    id SERIAL PRIMARY KEY,
+    api_key_id INTEGER NOT NULL REFERENCES api_keys (id),
+    action TEXT NOT NULL,
+    review_id INTEGER REFERENCES reviews (id) ON DELETE SET NULL,
+    target_id BIGINT NOT NULL,
+    reviewer_id BIGINT NOT NULL,
+    review_type review_type NOT NULL,
+    written_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX api_key_writes_key_idx ON api_key_writes (api_key_id, written_at DESC);
@@ -0,0 +1,12 @@
+-- Reviews that are not deleted and whose author is not blacklisted everywhere. The status is left to
+-- each query, since moderators and the revision history still see pending and hidden reviews.
+-- The column list is fixed when the view is created, so recreate it when reviews gains a column
+CREATE VIEW visible_reviews AS
+SELECT reviews.*
+FROM reviews
+WHERE deleted_at IS NULL
+    AND NOT EXISTS (
+        SELECT 1 FROM blacklist
+        WHERE blacklist.user_id = reviews.reviewer_id AND blacklist.scope = 'everywhere'
+            AND (blacklist.expires_at IS NULL OR blacklist.expires_at > CURRENT_TIMESTAMP)
+    );
@@ -0,0 +1,177 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    headers::{Cookie, HeaderMapExt},
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::marker::PhantomData;
+use tracing::error;
+use crate::{
+    api::{
+        routes::{AppState, ErrorResponse},
+        session::{SessionUser, SESSION_COOKIE},
+    },
+    app::{
+        models::api_key::{ApiKey, ApiScope},
+        utils::{api_keys, store::ReviewStore},
+    },
+};
+
+pub trait RequiredScope {
+    const SCOPE: ApiScope;
+}
+
+pub struct ReviewsRead;
+pub struct ReviewsWrite;
+pub struct Admin;
+
+impl RequiredScope for ReviewsRead {
+    const SCOPE: ApiScope = ApiScope::ReviewsRead;
+}
+
+impl RequiredScope for ReviewsWrite {
+    const SCOPE: ApiScope = ApiScope::ReviewsWrite;
+}
+
+impl RequiredScope for Admin {
+    const SCOPE: ApiScope = ApiScope::Admin;
+}
+
+pub enum Principal {
+    Key(ApiKey),
+    User(SessionUser),
+}
+
+impl Principal {
+    // Signed in users can read and write as themselves, but never administer
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        match self {
+            Principal::Key(key) => key.allows(scope),
+            Principal::User(_) => scope != ApiScope::Admin,
+        }
+    }
+
+    // Keys act for whichever user the caller names, users only ever act for themselves
+    #[allow(clippy::result_large_err)]
+    pub fn acting_as(&self, requested: Option<i64>) -> Result<i64, Response> {
+        match (self, requested) {
+            (Principal::User(user), Some(id)) if id != user.user_id => Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "You can only act as yourself".to_string(),
+                }),
+            )
+                .into_response()),
+            (Principal::User(user), _) => Ok(user.user_id),
+            (Principal::Key(_), Some(id)) => Ok(id),
+            (Principal::Key(_), None) => Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ErrorResponse {
+                    error: "reviewer_id is required when using an API key".to_string(),
+                }),
+            )
+                .into_response()),
+        }
+    }
+
+    pub fn api_key(&self) -> Option<&ApiKey> {
+        match self {
+            Principal::Key(key) => Some(key),
+            Principal::User(_) => None,
+        }
+    }
+}
+
+// Rejects the request unless it carries an unrevoked key or a session that grants `Sc`
+pub struct Authorized<Sc: RequiredScope>(pub Principal, pub PhantomData<fn() -> Sc>);
+
+enum Credential<'a> {
+    Key(&'a str),
+    Session(String),
+}
+
+fn presented_credential(parts: &Parts) -> Option<Credential<'_>> {
+    if let Some(key) = parts.headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(Credential::Key(key.trim()));
+    }
+
+    if let Some(bearer) = parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+    {
+        return Some(if bearer.starts_with(api_keys::KEY_PREFIX) {
+            Credential::Key(bearer)
+        } else {
+            Credential::Session(bearer.to_string())
+        });
+    }
+
+    parts
+        .headers
+        .typed_get::<Cookie>()
+        .and_then(|cookie| cookie.get(SESSION_COOKIE).map(str::to_string))
+        .map(Credential::Session)
+}
+
+fn unauthorized(error: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+#[async_trait]
+impl<Sc, S> FromRequestParts<AppState<S>> for Authorized<Sc>
+where
+    Sc: RequiredScope,
+    S: ReviewStore,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState<S>) -> Result<Self, Self::Rejection> {
+        let principal = match presented_credential(parts) {
+            Some(Credential::Key(key)) if !key.is_empty() => {
+                match state.db.authenticate_api_key(&api_keys::hash(key)).await {
+                    Ok(Some(api_key)) => Principal::Key(api_key),
+                    Ok(None) => return Err(unauthorized("Invalid or revoked API key")),
+                    Err(e) => {
+                        error!("Database error: {}", e);
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: "Failed to check API key".to_string(),
+                            }),
+                        )
+                            .into_response());
+                    }
+                }
+            }
+            Some(Credential::Session(token)) => match state.sessions.verify(&token) {
+                Some(user) => Principal::User(user),
+                None => return Err(unauthorized("Invalid or expired session")),
+            },
+            _ => return Err(unauthorized("Missing API key or session")),
+        };
+
+        if !principal.allows(Sc::SCOPE) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: format!("Missing the '{}' scope", Sc::SCOPE.as_str()),
+                }),
+            )
+                .into_response());
+        }
+
+        Ok(Authorized(principal, PhantomData))
+    }
+}
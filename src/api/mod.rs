@@ -1,2 +1,5 @@
-pub mod routes;
-pub mod server; 
\ No newline at end of file
+pub mod auth;
+pub mod oauth;
+pub mod routes;
+pub mod server;
+pub mod session;
\ No newline at end of file
@@ -0,0 +1,151 @@
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::{env, fmt, time::Duration};
+
+const DEFAULT_AUTHORIZE_URL: &str = "https://discord.com/oauth2/authorize";
+const DEFAULT_API_URL: &str = "https://discord.com/api/v10";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct OAuthConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub authorize_url: Url,
+    // Token exchange and the user lookup go here, so tests can point it at a mock server
+    pub api_url: String,
+    pub success_redirect: Option<String>,
+}
+
+impl OAuthConfig {
+    // Login stays off until the application credentials are set
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        Some(Self {
+            client_id: var("OAUTH_CLIENT_ID")?,
+            client_secret: var("OAUTH_CLIENT_SECRET")?,
+            redirect_uri: var("OAUTH_REDIRECT_URI")?,
+            authorize_url: var("OAUTH_AUTHORIZE_URL")
+                .and_then(|url| Url::parse(&url).ok())
+                .unwrap_or_else(|| Url::parse(DEFAULT_AUTHORIZE_URL).expect("Default authorize URL is valid")),
+            api_url: var("DISCORD_API_URL")
+                .unwrap_or_else(|| DEFAULT_API_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            success_redirect: var("OAUTH_SUCCESS_REDIRECT"),
+        })
+    }
+
+    pub fn secure_cookies(&self) -> bool {
+        self.redirect_uri.starts_with("https://")
+    }
+}
+
+#[derive(Debug)]
+pub enum OAuthError {
+    Request(reqwest::Error),
+    Rejected(reqwest::StatusCode),
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OAuthError::Request(e) => write!(f, "request to Discord failed: {}", e),
+            OAuthError::Rejected(status) => write!(f, "Discord answered with {}", status),
+        }
+    }
+}
+
+impl std::error::Error for OAuthError {}
+
+impl From<reqwest::Error> for OAuthError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => OAuthError::Rejected(status),
+            None => OAuthError::Request(e),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscordUser {
+    pub id: String,
+    pub username: String,
+}
+
+pub struct DiscordOAuth {
+    config: OAuthConfig,
+    client: Client,
+}
+
+impl DiscordOAuth {
+    pub fn new(config: OAuthConfig) -> Self {
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build the OAuth HTTP client");
+        Self { config, client }
+    }
+
+    pub fn config(&self) -> &OAuthConfig {
+        &self.config
+    }
+
+    pub fn authorize_url(&self, state: &str) -> Url {
+        let mut url = self.config.authorize_url.clone();
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", "identify")
+            .append_pair("state", state)
+            .append_pair("prompt", "none");
+        url
+    }
+
+    // Trades the code from the callback for an access token
+    pub async fn exchange_code(&self, code: &str) -> Result<String, OAuthError> {
+        let token: TokenResponse = self
+            .client
+            .post(format!("{}/oauth2/token", self.config.api_url))
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(token.access_token)
+    }
+
+    pub async fn fetch_user(&self, access_token: &str) -> Result<DiscordUser, OAuthError> {
+        let user = self
+            .client
+            .get(format!("{}/users/@me", self.config.api_url))
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(user)
+    }
+
+    // Runs the whole callback: code to token, token to the Discord account behind it
+    pub async fn identify(&self, code: &str) -> Result<DiscordUser, OAuthError> {
+        let access_token = self.exchange_code(code).await?;
+        self.fetch_user(&access_token).await
+    }
+}
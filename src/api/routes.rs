@@ -1,187 +1,1182 @@
-use axum::{
-    routing::{get, post},
-    Router,
-    extract::{Path, State},
-    Json,
-    http::StatusCode,
-};
-use crate::app::{
-    utils::{database::Database, cache::Cache},
-    models::review::{Review, ReviewType},
-};
-use serde::{Deserialize, Serialize};
-use tracing::error;
-
-#[derive(Clone)]
-pub struct AppState {
-    pub db: Database,
-}
-
-impl AppState {
-    pub fn new(db: Database, _cache: Cache) -> Self {
-        Self { db }
-    }
-}
-
-#[derive(Deserialize)]
-pub struct ReviewRequest {
-    pub rating: i32,
-    pub comment: Option<String>,
-    pub reviewer_id: i64,
-}
-
-#[derive(Serialize)]
-pub struct ReviewsResponse {
-    pub target_id: i64,
-    pub review_type: String,
-    pub average_rating: f64,
-    pub total_reviews: i64,
-    pub reviews: Vec<Review>,
-}
-
-#[derive(Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-}
-
-pub fn create_router() -> Router<AppState> {
-    Router::new()
-        .route("/reviews/:review_type/:target_id", get(get_reviews))
-        .route("/reviews/:review_type/:target_id", post(add_review)) // TODO: Add some sort of auth
-}
-
-async fn get_reviews(
-    State(state): State<AppState>,
-    Path((review_type_str, target_id)): Path<(String, i64)>,
-) -> Result<Json<ReviewsResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let review_type = match review_type_str.as_str() {
-        "user" => ReviewType::User,
-        "server" => ReviewType::Server,
-        _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Invalid review type".to_string(),
-                }),
-            ));
-        }
-    };
-
-    let reviews = match state.db.get_paginated_reviews(target_id, 0, 50, &review_type).await {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Database error: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to fetch reviews".to_string(),
-                }),
-            ));
-        }
-    };
-
-    let total_reviews = match state.db.get_reviews_count(target_id, &review_type).await {
-        Ok(count) => count,
-        Err(e) => {
-            error!("Database error: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to count reviews".to_string(),
-                }),
-            ));
-        }
-    };
-
-    let average_rating = match state.db.get_average_rating(target_id, &review_type).await {
-        Ok(avg) => avg.unwrap_or(0.0),
-        Err(e) => {
-            error!("Database error: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to calculate average rating".to_string(),
-                }),
-            ));
-        }
-    };
-
-    Ok(Json(ReviewsResponse {
-        target_id,
-        review_type: review_type_str,
-        average_rating,
-        total_reviews,
-        reviews,
-    }))
-}
-
-async fn add_review(
-    State(state): State<AppState>,
-    Path((review_type_str, target_id)): Path<(String, i64)>,
-    Json(payload): Json<ReviewRequest>,
-) -> Result<Json<Review>, (StatusCode, Json<ErrorResponse>)> {
-    let review_type = match review_type_str.as_str() {
-        "user" => ReviewType::User,
-        "server" => ReviewType::Server,
-        _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Invalid review type".to_string(),
-                }),
-            ));
-        }
-    };
-
-    match state.db.has_reviewed(target_id, payload.reviewer_id, &review_type).await {
-        Ok(true) => {
-            match state.db.update_review(
-                target_id,
-                payload.reviewer_id,
-                payload.rating,
-                payload.comment,
-                &review_type,
-            ).await {
-                Ok(review) => Ok(Json(review)),
-                Err(e) => {
-                    error!("Database error: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ErrorResponse {
-                            error: "Failed to update review".to_string(),
-                        }),
-                    ))
-                }
-            }
-        },
-        Ok(false) => {
-            match state.db.add_review(
-                target_id,
-                payload.reviewer_id,
-                payload.rating,
-                payload.comment,
-                &review_type,
-            ).await {
-                Ok(review) => Ok(Json(review)),
-                Err(e) => {
-                    error!("Database error: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ErrorResponse {
-                            error: "Failed to add review".to_string(),
-                        }),
-                    ))
-                }
-            }
-        },
-        Err(e) => {
-            error!("Database error: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to check existing review".to_string(),
-                }),
-            ))
-        }
-    }
-} 
\ No newline at end of file
+use axum::{
+    routing::{get, post, delete},
+    Router,
+    extract::{Path, Query, State},
+    Json,
+    headers::{Cookie, HeaderMapExt},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{AppendHeaders, IntoResponse, Redirect, Response},
+};
+use poise::serenity_prelude::Http;
+use rand::RngCore;
+use crate::api::{
+    auth::{self, Authorized, Principal},
+    oauth::{DiscordOAuth, OAuthConfig},
+    session::{SessionUser, Sessions, SESSION_COOKIE},
+};
+use crate::app::{
+    handlers::moderation,
+    utils::{
+        database::Database, store::ReviewStore, moderation::{ModerationPipeline, Verdict},
+        rate_limit::{self, RateLimiter}, blacklist, scoring::ScoreConfig,
+        validation::{self, ReviewDraft, ValidReview, ValidationErrors},
+    },
+    models::{
+        api_key::{ApiKey, ApiScope},
+        review::{
+            Review, ReviewRevision, ReviewStatus, ReviewType, RatingDistribution, RatingCategory, ScoreView, ReviewSort,
+            ReviewQuery,
+        },
+    },
+};
+use std::{collections::BTreeMap, sync::Arc};
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::error;
+
+const LEADERBOARD_SIZE: usize = 25;
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 100;
+const OAUTH_STATE_COOKIE: &str = "oauth_state";
+// Seconds a login attempt has to come back from Discord
+const OAUTH_STATE_TTL: i64 = 600;
+
+#[derive(Clone)]
+pub struct AppState<S: ReviewStore = Database> {
+    pub db: S,
+    pub moderation: Arc<ModerationPipeline>,
+    pub http: Arc<Http>,
+    pub rate_limiter: RateLimiter,
+    pub oauth: Option<Arc<DiscordOAuth>>,
+    pub sessions: Sessions,
+    pub scoring: ScoreConfig,
+}
+
+impl<S: ReviewStore> AppState<S> {
+    pub fn new(
+        db: S,
+        moderation: Arc<ModerationPipeline>,
+        http: Arc<Http>,
+        rate_limiter: RateLimiter,
+        scoring: ScoreConfig,
+    ) -> Self {
+        Self {
+            db,
+            moderation,
+            http,
+            rate_limiter,
+            oauth: OAuthConfig::from_env().map(|config| Arc::new(DiscordOAuth::new(config))),
+            sessions: Sessions::from_env(),
+            scoring,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    pub rating: Option<i32>,
+    pub comment: Option<String>,
+    // Only API keys may name the reviewer, signed in users always review as themselves
+    pub reviewer_id: Option<i64>,
+    #[serde(default)]
+    pub criteria: BTreeMap<String, i32>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteParams {
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewsParams {
+    pub view: Option<String>,
+    pub sort: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub min_rating: Option<i32>,
+    pub max_rating: Option<i32>,
+    pub has_comment: Option<bool>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub token: String,
+    pub user_id: i64,
+    pub username: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+#[derive(Serialize)]
+pub struct ValidationErrorResponse {
+    pub error: String,
+    pub fields: ValidationErrors,
+}
+
+#[derive(Serialize)]
+pub struct ReviewsResponse {
+    pub target_id: i64,
+    pub review_type: String,
+    pub average_rating: f64,
+    pub score: f64,
+    pub score_view: ScoreView,
+    pub category: String,
+    pub total_reviews: i64,
+    pub distribution: RatingDistribution,
+    pub criteria: BTreeMap<String, f64>,
+    pub flagged: bool,
+    pub average_frozen: bool,
+    pub page: i64,
+    pub per_page: i64,
+    pub matching_reviews: i64,
+    // The page to ask for next, None on the last page
+    pub next: Option<i64>,
+    pub reviews: Vec<Review>,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub target_id: i64,
+    pub score: f64,
+    pub average_rating: f64,
+    pub total_reviews: i64,
+}
+
+#[derive(Serialize)]
+pub struct RevisionsResponse {
+    pub review: Review,
+    pub revisions: Vec<ReviewRevision>,
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+pub fn create_router<S: ReviewStore>() -> Router<AppState<S>> {
+    Router::new()
+        .route("/reviews/:review_type/:target_id", get(get_reviews::<S>))
+        .route("/reviews/:review_type/:target_id", post(add_review::<S>))
+        .route("/reviews/:review_type/:target_id/:reviewer_id", delete(delete_review::<S>))
+        .route("/reviews/:review_type/:target_id/:reviewer_id/revisions", get(get_revisions::<S>))
+        .route("/leaderboard/:review_type", get(get_leaderboard::<S>))
+        .route("/api-keys", get(list_api_keys::<S>))
+        .route("/auth/login", get(login::<S>))
+        .route("/auth/callback", get(oauth_callback::<S>))
+        .route("/auth/me", get(current_user))
+        .route("/auth/logout", post(logout::<S>))
+}
+
+fn parse_review_type(review_type: &str) -> Result<ReviewType, (StatusCode, Json<ErrorResponse>)> {
+    ReviewType::parse(review_type).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid review type".to_string(),
+            }),
+        )
+    })
+}
+
+fn bad_query(error: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+}
+
+fn review_query(params: &ReviewsParams) -> Result<ReviewQuery, (StatusCode, Json<ErrorResponse>)> {
+    let sort = match params.sort.as_deref() {
+        Some(sort) => ReviewSort::parse(sort)
+            .ok_or_else(|| bad_query("Invalid sort, expected newest, oldest, highest, lowest or helpful"))?,
+        None => ReviewSort::default(),
+    };
+
+    let rating_range = validation::MIN_RATING..=validation::MAX_RATING;
+    for rating in [params.min_rating, params.max_rating].into_iter().flatten() {
+        if !rating_range.contains(&rating) {
+            return Err(bad_query("min_rating and max_rating must be between 1 and 5"));
+        }
+    }
+    if let (Some(min), Some(max)) = (params.min_rating, params.max_rating) {
+        if min > max {
+            return Err(bad_query("min_rating cannot be above max_rating"));
+        }
+    }
+
+    let timestamp = |value: &Option<String>, name: &str| match value.as_deref() {
+        Some(value) => OffsetDateTime::parse(value, &Rfc3339)
+            .map(Some)
+            .map_err(|_| bad_query(&format!("Invalid {}, expected an RFC 3339 timestamp", name))),
+        None => Ok(None),
+    };
+
+    Ok(ReviewQuery {
+        sort,
+        min_rating: params.min_rating,
+        max_rating: params.max_rating,
+        has_comment: params.has_comment,
+        since: timestamp(&params.since, "since")?,
+        until: timestamp(&params.until, "until")?,
+    })
+}
+
+// Keeps every other parameter of the request as it was sent and only swaps the page
+fn page_link(uri: &Uri, page: i64, rel: &str) -> String {
+    let page_param = format!("page={}", page);
+    let query: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty() && !p.starts_with("page="))
+        .chain(std::iter::once(page_param.as_str()))
+        .collect();
+    format!("<{}?{}>; rel=\"{}\"", uri.path(), query.join("&"), rel)
+}
+
+async fn get_reviews<S: ReviewStore>(
+    _: Authorized<auth::ReviewsRead>,
+    State(state): State<AppState<S>>,
+    Path((review_type_str, target_id)): Path<(String, i64)>,
+    uri: Uri,
+    Query(params): Query<ReviewsParams>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let review_type = parse_review_type(&review_type_str)?;
+    let view = match params.view.as_deref() {
+        Some(view) => ScoreView::parse(view).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid view, expected all_time, recent or decayed".to_string(),
+                }),
+            )
+        })?,
+        None => ScoreView::default(),
+    };
+    let query = review_query(&params)?;
+    let per_page = params.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        return Err(bad_query("page starts at 1"));
+    }
+    if (page - 1).checked_mul(per_page).is_none() {
+        return Err(bad_query("page is too large"));
+    }
+
+    // Pages are numbered from 1 here and from 0 in the store
+    let reviews = match state.db.get_paginated_reviews(target_id, page - 1, per_page, &review_type, &query).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch reviews".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let matching_reviews = match state.db.count_matching_reviews(target_id, &review_type, &query).await {
+        Ok(count) => count,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to count reviews".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let total_reviews = match state.db.get_reviews_count(target_id, &review_type).await {
+        Ok(count) => count,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to count reviews".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let average_rating = match state.db.get_average_rating(target_id, &review_type).await {
+        Ok(avg) => avg.unwrap_or(0.0),
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to calculate average rating".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let distribution = match state.db.get_rating_distribution(target_id, &review_type).await {
+        Ok(distribution) => distribution,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch rating distribution".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let criteria = match criteria_averages(&state.db, target_id, &review_type).await {
+        Ok(criteria) => criteria,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch criteria ratings".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let flag = match state.db.get_target_flag(target_id, &review_type).await {
+        Ok(flag) => flag,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch reviews".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let score = match state.scoring.target_score(&state.db, target_id, &review_type, view, average_rating, total_reviews).await {
+        Ok(score) => score,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to calculate score".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let last_page = ((matching_reviews + per_page - 1) / per_page).max(1);
+    let next = (page < last_page).then_some(page + 1);
+    let mut links = vec![page_link(&uri, 1, "first"), page_link(&uri, last_page, "last")];
+    if let Some(next) = next {
+        links.push(page_link(&uri, next, "next"));
+    }
+    if page > 1 {
+        links.push(page_link(&uri, (page - 1).min(last_page), "prev"));
+    }
+
+    let body = Json(ReviewsResponse {
+        target_id,
+        review_type: review_type_str,
+        average_rating,
+        score,
+        score_view: view,
+        category: RatingCategory::from_average(score).to_string(),
+        total_reviews,
+        distribution,
+        criteria,
+        flagged: flag.is_some(),
+        average_frozen: flag.is_some_and(|f| f.frozen_average.is_some()),
+        page,
+        per_page,
+        matching_reviews,
+        next,
+        reviews,
+    });
+
+    Ok(([(header::LINK, links.join(", "))], body).into_response())
+}
+
+async fn get_leaderboard<S: ReviewStore>(
+    _: Authorized<auth::ReviewsRead>,
+    State(state): State<AppState<S>>,
+    Path(review_type_str): Path<String>,
+) -> Result<Json<Vec<LeaderboardEntry>>, (StatusCode, Json<ErrorResponse>)> {
+    let review_type = parse_review_type(&review_type_str)?;
+
+    let ranked = match state.scoring.leaderboard(&state.db, &review_type, LEADERBOARD_SIZE).await {
+        Ok(ranked) => ranked,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to rank targets".to_string(),
+                }),
+            ));
+        }
+    };
+
+    Ok(Json(
+        ranked
+            .into_iter()
+            .enumerate()
+            .map(|(i, (target, score))| LeaderboardEntry {
+                rank: i + 1,
+                target_id: target.target_id,
+                score,
+                average_rating: target.average,
+                total_reviews: target.review_count,
+            })
+            .collect(),
+    ))
+}
+
+async fn criteria_averages<S: ReviewStore>(
+    db: &S,
+    target_id: i64,
+    review_type: &ReviewType,
+) -> Result<BTreeMap<String, f64>, sqlx::Error> {
+    let keys: Vec<String> = db
+        .get_criteria(review_type, None)
+        .await?
+        .into_iter()
+        .map(|c| c.key)
+        .collect();
+    db.get_criteria_averages(target_id, review_type, &keys).await
+}
+
+async fn add_review<S: ReviewStore>(
+    Authorized(principal, _): Authorized<auth::ReviewsWrite>,
+    State(state): State<AppState<S>>,
+    Path((review_type_str, target_id)): Path<(String, i64)>,
+    Json(payload): Json<ReviewRequest>,
+) -> Result<(StatusCode, Json<Review>), Response> {
+    let reviewer_id = principal.acting_as(payload.reviewer_id)?;
+
+    let save_failed = |e: sqlx::Error| {
+        error!("Database error: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to save review".to_string(),
+            }),
+        )
+            .into_response()
+    };
+
+    // The API is not tied to a guild, so only the default criteria are accepted
+    let definitions = match ReviewType::parse(&review_type_str) {
+        Some(review_type) => state.db.get_criteria(&review_type, None).await.map_err(save_failed)?,
+        None => Vec::new(),
+    };
+    let draft = ReviewDraft {
+        review_type: &review_type_str,
+        target_id,
+        reviewer_id,
+        rating: payload.rating,
+        comment: payload.comment.as_deref(),
+        criteria: payload.criteria.into_iter().map(|(key, rating)| (key, Some(rating))).collect(),
+    };
+    let ValidReview { review_type, rating, comment, criteria } = validation::validate_review(draft, &definitions)
+        .map_err(|fields| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ValidationErrorResponse {
+                    error: "Invalid review".to_string(),
+                    fields,
+                }),
+            )
+                .into_response()
+        })?;
+
+    if let Some(denial) = blacklist::review_denial(&state.db, reviewer_id, target_id, &review_type)
+        .await
+        .map_err(save_failed)?
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: denial.to_string(),
+            }),
+        )
+            .into_response());
+    }
+
+    let (status, held) = match state.moderation.review(comment.as_deref()) {
+        Verdict::Allow => (ReviewStatus::Published, None),
+        Verdict::Hold(reason) => (ReviewStatus::Pending, Some(reason)),
+        Verdict::Reject(reason) => {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ErrorResponse {
+                    error: format!("Review rejected: comment {}", reason),
+                }),
+            )
+                .into_response());
+        }
+    };
+
+    // Taken last, so only a review that is about to be saved uses up a slot
+    let editing = state.db
+        .has_reviewed(target_id, reviewer_id, &review_type)
+        .await
+        .map_err(save_failed)?;
+    if let Some(wait) = state.rate_limiter.acquire(reviewer_id, target_id, &review_type, editing).await {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, wait.to_string())],
+            Json(ErrorResponse {
+                error: format!("Too many reviews, try again in {}", rate_limit::format_wait(wait)),
+            }),
+        )
+            .into_response());
+    }
+
+    let (review, created) = state.db.upsert_review(
+        target_id,
+        reviewer_id,
+        rating,
+        comment,
+        criteria,
+        &review_type,
+        status,
+    )
+    .await
+    .map_err(save_failed)?;
+
+    if let Some(api_key) = principal.api_key() {
+        if let Err(e) = state.db
+            .record_api_write(api_key.id, "review_upsert", Some(review.id), target_id, reviewer_id, &review_type)
+            .await
+        {
+            error!("Failed to record API write: {}", e);
+        }
+    }
+
+    if let Some(reason) = held {
+        if let Err(e) = moderation::post_pending_review(&state.http, &review, &reason).await {
+            error!("Failed to queue held review: {}", e);
+        }
+        return Ok((StatusCode::ACCEPTED, Json(review)));
+    }
+
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(review)))
+}
+
+async fn get_revisions<S: ReviewStore>(
+    Authorized(principal, _): Authorized<auth::ReviewsRead>,
+    State(state): State<AppState<S>>,
+    Path((review_type_str, target_id, reviewer_id)): Path<(String, i64, i64)>,
+) -> Result<Json<RevisionsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let review_type = parse_review_type(&review_type_str)?;
+
+    // Held and hidden reviews keep their history, so this lookup ignores the status
+    let review = match state.db.get_review(target_id, reviewer_id, &review_type).await {
+        Ok(Some(review)) => review,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Review not found".to_string(),
+                }),
+            ));
+        }
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch review".to_string(),
+                }),
+            ));
+        }
+    };
+
+    // Only admins see the history of reviews that are not public
+    if !principal.allows(ApiScope::Admin) {
+        match state.db.get_review_status(review.id).await {
+            Ok(Some(ReviewStatus::Published)) => {}
+            Ok(_) => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Review not found".to_string(),
+                    }),
+                ));
+            }
+            Err(e) => {
+                error!("Database error: {}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to fetch review".to_string(),
+                    }),
+                ));
+            }
+        }
+    }
+
+    match state.db.get_review_revisions(review.id).await {
+        Ok(revisions) => Ok(Json(RevisionsResponse { review, revisions })),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch revisions".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+async fn delete_review<S: ReviewStore>(
+    Authorized(principal, _): Authorized<auth::ReviewsWrite>,
+    State(state): State<AppState<S>>,
+    Path((review_type_str, target_id, reviewer_id)): Path<(String, i64, i64)>,
+    Query(params): Query<DeleteParams>,
+) -> Result<StatusCode, Response> {
+    let review_type = parse_review_type(&review_type_str).map_err(IntoResponse::into_response)?;
+    principal.acting_as(Some(reviewer_id))?;
+    // A key deletes on behalf of whoever created it, and only admin keys may delete other people's reviews
+    let deleted_by = match principal {
+        Principal::Key(ref key) if key.created_by != reviewer_id && !key.allows(ApiScope::Admin) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Deleting another user's review requires the admin scope".to_string(),
+                }),
+            )
+                .into_response());
+        }
+        Principal::Key(ref key) => key.created_by,
+        Principal::User(ref user) => user.user_id,
+    };
+
+    match state.db.delete_review(target_id, reviewer_id, &review_type, deleted_by, params.reason).await {
+        Ok(true) => {
+            if let Some(api_key) = principal.api_key() {
+                if let Err(e) = state.db
+                    .record_api_write(api_key.id, "review_delete", None, target_id, reviewer_id, &review_type)
+                    .await
+                {
+                    error!("Failed to record API write: {}", e);
+                }
+            }
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Review not found".to_string(),
+            }),
+        )
+            .into_response()),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to delete review".to_string(),
+                }),
+            )
+                .into_response())
+        }
+    }
+}
+
+async fn list_api_keys<S: ReviewStore>(
+    _: Authorized<auth::Admin>,
+    State(state): State<AppState<S>>,
+) -> Result<Json<Vec<ApiKey>>, (StatusCode, Json<ErrorResponse>)> {
+    match state.db.list_api_keys().await {
+        Ok(keys) => Ok(Json(keys)),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to fetch API keys".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+fn oauth_client<S: ReviewStore>(state: &AppState<S>) -> Result<Arc<DiscordOAuth>, (StatusCode, Json<ErrorResponse>)> {
+    state.oauth.clone().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Discord login is not configured".to_string(),
+            }),
+        )
+    })
+}
+
+fn cookie(name: &str, value: &str, max_age: i64, path: &str, secure: bool) -> String {
+    format!(
+        "{}={}; Max-Age={}; Path={}; HttpOnly; SameSite=Lax{}",
+        name,
+        value,
+        max_age,
+        path,
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+async fn login<S: ReviewStore>(
+    State(state): State<AppState<S>>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let oauth = oauth_client(&state)?;
+
+    // Ties the callback to this browser so a login cannot be started elsewhere and finished here
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let login_state = hex::encode(bytes);
+
+    let state_cookie = cookie(
+        OAUTH_STATE_COOKIE,
+        &login_state,
+        OAUTH_STATE_TTL,
+        "/auth",
+        oauth.config().secure_cookies(),
+    );
+    Ok((
+        [(header::SET_COOKIE, state_cookie)],
+        Redirect::to(oauth.authorize_url(&login_state).as_str()),
+    )
+        .into_response())
+}
+
+async fn oauth_callback<S: ReviewStore>(
+    State(state): State<AppState<S>>,
+    headers: HeaderMap,
+    Query(params): Query<CallbackParams>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let oauth = oauth_client(&state)?;
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+
+    if let Some(error) = params.error {
+        return Err(bad_request(format!("Discord login failed: {}", error)));
+    }
+
+    let expected = headers
+        .typed_get::<Cookie>()
+        .and_then(|cookie| cookie.get(OAUTH_STATE_COOKIE).map(str::to_string));
+    if expected.is_none() || params.state != expected {
+        return Err(bad_request("Login expired or was started elsewhere, please try again".to_string()));
+    }
+    let code = params
+        .code
+        .ok_or_else(|| bad_request("Missing authorization code".to_string()))?;
+
+    let login_failed = || {
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                error: "Failed to sign in with Discord".to_string(),
+            }),
+        )
+    };
+    let discord_user = oauth.identify(&code).await.map_err(|e| {
+        error!("Discord login failed: {}", e);
+        login_failed()
+    })?;
+    let user = SessionUser {
+        user_id: discord_user.id.parse().map_err(|_| login_failed())?,
+        username: discord_user.username,
+    };
+
+    let (token, expires_at) = state.sessions.issue(&user).map_err(|e| {
+        error!("Failed to issue session: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to start session".to_string(),
+            }),
+        )
+    })?;
+
+    let secure = oauth.config().secure_cookies();
+    let cookies = AppendHeaders([
+        (header::SET_COOKIE, cookie(SESSION_COOKIE, &token, state.sessions.ttl.whole_seconds(), "/", secure)),
+        (header::SET_COOKIE, cookie(OAUTH_STATE_COOKIE, "", 0, "/auth", secure)),
+    ]);
+
+    Ok(match &oauth.config().success_redirect {
+        Some(url) => (cookies, Redirect::to(url)).into_response(),
+        None => (
+            cookies,
+            Json(SessionResponse {
+                token,
+                user_id: user.user_id,
+                username: user.username,
+                expires_at,
+            }),
+        )
+            .into_response(),
+    })
+}
+
+async fn current_user(
+    Authorized(principal, _): Authorized<auth::ReviewsRead>,
+) -> Result<Json<SessionUser>, (StatusCode, Json<ErrorResponse>)> {
+    match principal {
+        Principal::User(user) => Ok(Json(user)),
+        Principal::Key(_) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "API keys do not belong to a user".to_string(),
+            }),
+        )),
+    }
+}
+
+// Sessions are signed tokens, so this only drops the cookie, bearer tokens stay valid until they expire
+async fn logout<S: ReviewStore>(State(state): State<AppState<S>>) -> impl IntoResponse {
+    // Browsers only replace the cookie when the attributes match the ones it was set with
+    let secure = state.oauth.as_ref().is_some_and(|oauth| oauth.config().secure_cookies());
+    (
+        [(header::SET_COOKIE, cookie(SESSION_COOKIE, "", 0, "/", secure))],
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::{HeaderMap, Request, header}};
+    use serde_json::{json, Value};
+    use time::{Duration, OffsetDateTime};
+    use tower::ServiceExt;
+    use crate::app::utils::{
+        api_keys,
+        cache::NoCache,
+        memory_store::MemoryStore,
+        moderation::WordListCheck,
+        rate_limit::RateLimitRules,
+        scoring::ScoringMode,
+    };
+
+    const TARGET: i64 = 10;
+    const REVIEWER: i64 = 20;
+
+    fn state() -> AppState<MemoryStore> {
+        AppState {
+            db: MemoryStore::default(),
+            moderation: Arc::new(ModerationPipeline::new(Vec::new())),
+            http: Arc::new(Http::new("")),
+            rate_limiter: RateLimiter::new(
+                Arc::new(NoCache),
+                RateLimitRules {
+                    reviews_per_hour: 0,
+                    edit_gap: 0,
+                    target_per_hour: 0,
+                },
+            ),
+            oauth: None,
+            sessions: Sessions::new(b"test secret", Duration::hours(1)),
+            scoring: ScoreConfig {
+                mode: ScoringMode::Bayesian,
+                prior_weight: 5.0,
+                half_life: Duration::days(180),
+            },
+        }
+    }
+
+    async fn api_key(state: &AppState<MemoryStore>, scopes: &[&str]) -> String {
+        let issued = api_keys::generate();
+        let scopes = scopes.iter().map(|s| s.to_string()).collect();
+        state.db.create_api_key("test".to_string(), issued.prefix, issued.hash, scopes, 1).await.unwrap();
+        issued.key
+    }
+
+    fn session(state: &AppState<MemoryStore>, user_id: i64) -> String {
+        let user = SessionUser {
+            user_id,
+            username: "tester".to_string(),
+        };
+        state.sessions.issue(&user).unwrap().0
+    }
+
+    async fn send(state: &AppState<MemoryStore>, request: Request<Body>) -> (StatusCode, HeaderMap, Value) {
+        let response = create_router().with_state(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let json = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        (status, headers, json)
+    }
+
+    fn request(method: &str, uri: &str, credential: &str, body: Option<Value>) -> Request<Body> {
+        let builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", credential));
+        match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_and_underscoped_credentials() {
+        let state = state();
+        let uri = format!("/reviews/user/{}", TARGET);
+
+        let anonymous = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+        let (status, headers, _) = send(&state, anonymous).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(headers[header::WWW_AUTHENTICATE], "Bearer");
+
+        let key = api_key(&state, &["reviews:read"]).await;
+        let body = json!({ "rating": 5, "reviewer_id": REVIEWER });
+        let (status, _, _) = send(&state, request("POST", &uri, &key, Some(body))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _, _) = send(&state, request("GET", "/api-keys", &key, None)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn creates_updates_and_lists_reviews() {
+        let state = state();
+        let key = api_key(&state, &["reviews:read", "reviews:write"]).await;
+        let uri = format!("/reviews/user/{}", TARGET);
+
+        let body = json!({ "rating": 4, "comment": "Smooth trade", "reviewer_id": REVIEWER });
+        let (status, _, review) = send(&state, request("POST", &uri, &key, Some(body))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(review["rating"], 4);
+
+        let body = json!({ "rating": 2, "comment": "Went wrong later", "reviewer_id": REVIEWER });
+        let (status, _, _) = send(&state, request("POST", &uri, &key, Some(body))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _, page) = send(&state, request("GET", &uri, &key, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total_reviews"], 1);
+        assert_eq!(page["reviews"][0]["comment"], "Went wrong later");
+    }
+
+    #[tokio::test]
+    async fn rejects_pages_out_of_range() {
+        let state = state();
+        let key = api_key(&state, &["reviews:read"]).await;
+
+        for page in ["0", "9223372036854775807"] {
+            let uri = format!("/reviews/user/{}?page={}&per_page=100", TARGET, page);
+            let (status, _, _) = send(&state, request("GET", &uri, &key, None)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_every_invalid_field() {
+        let state = state();
+        let key = api_key(&state, &["reviews:write"]).await;
+
+        let body = json!({ "rating": 9, "comment": "ok", "reviewer_id": REVIEWER, "criteria": { "unknown": 3 } });
+        let (status, _, error) = send(&state, request("POST", "/reviews/user/10", &key, Some(body))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let fields: Vec<&str> = error["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["rating", "comment", "criteria.unknown"]);
+        assert!(!state.db.has_reviewed(TARGET, REVIEWER, &ReviewType::User).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn sessions_only_act_as_their_user() {
+        let state = state();
+        let token = session(&state, REVIEWER);
+        let uri = format!("/reviews/user/{}", TARGET);
+
+        let body = json!({ "rating": 5, "reviewer_id": REVIEWER + 1 });
+        let (status, _, _) = send(&state, request("POST", &uri, &token, Some(body))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _, review) = send(&state, request("POST", &uri, &token, Some(json!({ "rating": 5 })))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(review["reviewer_id"], REVIEWER);
+
+        let (status, _, user) = send(&state, request("GET", "/auth/me", &token, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(user["user_id"], REVIEWER);
+
+        let (status, _, _) = send(&state, request("GET", "/api-keys", &token, None)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn soft_deletes_reviews() {
+        let state = state();
+        let key = api_key(&state, &["reviews:write"]).await;
+        let body = json!({ "rating": 3, "reviewer_id": REVIEWER });
+        let (_, _, review) = send(&state, request("POST", "/reviews/user/10", &key, Some(body))).await;
+
+        let uri = format!("/reviews/user/{}/{}?deleted_by=99&reason=spam", TARGET, REVIEWER);
+        let (status, _, _) = send(&state, request("DELETE", &uri, &key, None)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let key = api_key(&state, &["admin"]).await;
+        let (status, _, _) = send(&state, request("DELETE", &uri, &key, None)).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        // Recorded against the key's creator, not whoever the query string names
+        let deletion = state.db.deletion(review["id"].as_i64().unwrap() as i32).unwrap();
+        assert_eq!(deletion.deleted_by, 1);
+        assert_eq!(deletion.reason.as_deref(), Some("spam"));
+        assert!(deletion.deleted_at <= OffsetDateTime::now_utc());
+
+        let (status, _, _) = send(&state, request("DELETE", &uri, &key, None)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn lists_revisions_of_held_reviews_to_admins_only() {
+        let mut state = state();
+        state.moderation = Arc::new(ModerationPipeline::new(vec![Box::new(WordListCheck::new(
+            Vec::new(),
+            vec!["refund".to_string()],
+        ))]));
+        let key = api_key(&state, &["reviews:read", "reviews:write"]).await;
+        let uri = format!("/reviews/user/{}", TARGET);
+
+        let body = json!({ "rating": 2, "comment": "Still waiting", "reviewer_id": REVIEWER });
+        send(&state, request("POST", &uri, &key, Some(body))).await;
+        let body = json!({ "rating": 1, "comment": "Asked for a refund", "reviewer_id": REVIEWER });
+        let (status, _, _) = send(&state, request("POST", &uri, &key, Some(body))).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let (_, _, page) = send(&state, request("GET", &uri, &key, None)).await;
+        assert_eq!(page["total_reviews"], 0);
+
+        let revisions = format!("/reviews/user/{}/{}/revisions", TARGET, REVIEWER);
+        let (status, _, _) = send(&state, request("GET", &revisions, &key, None)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let admin = api_key(&state, &["reviews:read", "admin"]).await;
+        let (status, _, history) = send(&state, request("GET", &revisions, &admin, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(history["review"]["rating"], 1);
+        assert_eq!(history["revisions"][0]["rating"], 2);
+    }
+
+    // Stands in for the two Discord endpoints the callback calls
+    async fn mock_discord() -> String {
+        let discord = Router::new()
+            .route(
+                "/oauth2/token",
+                post(|axum::Form(form): axum::Form<BTreeMap<String, String>>| async move {
+                    match form.get("code").map(String::as_str) {
+                        Some("good-code") => Ok(Json(json!({ "access_token": "discord-token" }))),
+                        _ => Err(StatusCode::BAD_REQUEST),
+                    }
+                }),
+            )
+            .route(
+                "/users/@me",
+                get(|headers: HeaderMap| async move {
+                    match headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()) {
+                        Some("Bearer discord-token") => Ok(Json(json!({ "id": "42", "username": "tester" }))),
+                        _ => Err(StatusCode::UNAUTHORIZED),
+                    }
+                }),
+            );
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(discord.into_make_service()));
+        format!("http://{}", address)
+    }
+
+    fn with_oauth(mut state: AppState<MemoryStore>, api_url: String) -> AppState<MemoryStore> {
+        state.oauth = Some(Arc::new(DiscordOAuth::new(OAuthConfig {
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "https://reviews.example/auth/callback".to_string(),
+            authorize_url: reqwest::Url::parse("https://discord.example/oauth2/authorize").unwrap(),
+            api_url,
+            success_redirect: None,
+        })));
+        state
+    }
+
+    fn callback(code: &str, login_state: &str, cookie: &str) -> Request<Body> {
+        Request::builder()
+            .uri(format!("/auth/callback?code={}&state={}", code, login_state))
+            .header(header::COOKIE, format!("{}={}", OAUTH_STATE_COOKIE, cookie))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn set_cookies(headers: &HeaderMap) -> Vec<&str> {
+        headers.get_all(header::SET_COOKIE).iter().map(|v| v.to_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn signs_in_through_discord() {
+        let state = with_oauth(state(), mock_discord().await);
+
+        let (status, headers, session) = send(&state, callback("good-code", "abc", "abc")).await;
+        assert_eq!(status, StatusCode::OK, "{}", session);
+        assert_eq!(session["user_id"], 42);
+        assert_eq!(session["username"], "tester");
+
+        let cookies = set_cookies(&headers);
+        let token = session["token"].as_str().unwrap();
+        assert!(cookies.iter().any(|c| c.starts_with(&format!("session={};", token)) && c.ends_with("; Secure")));
+        assert!(cookies.iter().any(|c| c.starts_with("oauth_state=;") && c.contains("Max-Age=0")));
+
+        let (status, _, user) = send(&state, request("GET", "/auth/me", token, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(user["user_id"], 42);
+    }
+
+    #[tokio::test]
+    async fn refuses_mismatched_state_and_bad_codes() {
+        let state = with_oauth(state(), mock_discord().await);
+
+        let (status, _, _) = send(&state, callback("good-code", "abc", "other")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, headers, _) = send(&state, callback("bad-code", "abc", "abc")).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(set_cookies(&headers).is_empty());
+    }
+
+    #[tokio::test]
+    async fn logout_clears_the_cookie_login_set() {
+        let state = with_oauth(state(), mock_discord().await);
+
+        let logout = Request::builder().method("POST").uri("/auth/logout").body(Body::empty()).unwrap();
+        let (status, headers, _) = send(&state, logout).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(set_cookies(&headers), ["session=; Max-Age=0; Path=/; HttpOnly; SameSite=Lax; Secure"]);
+    }
+}
\ No newline at end of file
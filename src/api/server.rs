@@ -1,17 +1,27 @@
-use axum::Router;
-use tower_http::cors::{CorsLayer, Any};
-use crate::app::utils::{database::Database, cache::Cache};
-use crate::api::routes::AppState;
-
-pub async fn create_server(db: Database, cache: Cache) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    let state = AppState::new(db, cache);
-    
-    crate::api::routes::create_router()
-        .with_state(state)
-        .layer(cors)
+use axum::Router;
+use tower_http::cors::{CorsLayer, Any};
+use std::sync::Arc;
+use poise::serenity_prelude::Http;
+use crate::app::utils::{
+    store::ReviewStore, moderation::ModerationPipeline, rate_limit::RateLimiter, scoring::ScoreConfig,
+};
+use crate::api::routes::AppState;
+
+pub async fn create_server<S: ReviewStore>(
+    db: S,
+    moderation: Arc<ModerationPipeline>,
+    http: Arc<Http>,
+    rate_limiter: RateLimiter,
+    scoring: ScoreConfig,
+) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    let state = AppState::new(db, moderation, http, rate_limiter, scoring);
+    
+    crate::api::routes::create_router()
+        .with_state(state)
+        .layer(cors)
 }
\ No newline at end of file
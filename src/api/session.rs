@@ -0,0 +1,83 @@
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::env;
+use time::{Duration, OffsetDateTime};
+use tracing::warn;
+
+pub const SESSION_COOKIE: &str = "session";
+const DEFAULT_TTL_HOURS: i64 = 24 * 7;
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    name: String,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionUser {
+    pub user_id: i64,
+    pub username: String,
+}
+
+// Sessions are signed tokens, so they stay valid until they expire or the secret changes
+#[derive(Clone)]
+pub struct Sessions {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    pub ttl: Duration,
+}
+
+impl Sessions {
+    pub fn new(secret: &[u8], ttl: Duration) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            ttl,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let secret = match env::var("SESSION_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+            _ => {
+                warn!("SESSION_SECRET is not set, logins will not survive a restart");
+                let mut secret = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut secret);
+                secret
+            }
+        };
+        let ttl_hours = env::var("SESSION_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|h| *h > 0)
+            .unwrap_or(DEFAULT_TTL_HOURS);
+
+        Self::new(&secret, Duration::hours(ttl_hours))
+    }
+
+    pub fn issue(&self, user: &SessionUser) -> Result<(String, OffsetDateTime), jsonwebtoken::errors::Error> {
+        let now = OffsetDateTime::now_utc();
+        let expires_at = now + self.ttl;
+        let claims = Claims {
+            sub: user.user_id.to_string(),
+            name: user.username.clone(),
+            iat: now.unix_timestamp(),
+            exp: expires_at.unix_timestamp(),
+        };
+
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)?;
+        Ok((token, expires_at))
+    }
+
+    pub fn verify(&self, token: &str) -> Option<SessionUser> {
+        let data = decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256)).ok()?;
+
+        Some(SessionUser {
+            user_id: data.claims.sub.parse().ok()?,
+            username: data.claims.name,
+        })
+    }
+}
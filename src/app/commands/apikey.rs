@@ -0,0 +1,107 @@
+use crate::app::{
+    commands::review::Data,
+    models::api_key::ApiScope,
+    utils::{api_keys, store::ReviewStore},
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+#[poise::command(slash_command, owners_only, subcommands("create", "revoke", "list"))]
+pub async fn apikey(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use one of the subcommands: `/apikey create`, `/apikey revoke` or `/apikey list`").await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only)]
+pub async fn create(
+    ctx: Context<'_>,
+    #[description = "What the key is for"] name: String,
+    #[description = "What the key may do"] scope: ApiScope,
+    #[description = "Another scope to grant"] extra_scope: Option<ApiScope>,
+) -> Result<(), Error> {
+    let mut scopes = vec![scope.as_str().to_string()];
+    if let Some(extra) = extra_scope.filter(|s| *s != scope) {
+        scopes.push(extra.as_str().to_string());
+    }
+
+    let issued = api_keys::generate();
+    let key = ctx.data().db.create_api_key(
+        name,
+        issued.prefix,
+        issued.hash,
+        scopes,
+        ctx.author().id.0 as i64,
+    ).await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            "✅ Created API key #{} `{}` ({})\n```\n{}\n```\nCopy it now, it will not be shown again.",
+            key.id,
+            key.name,
+            key.scopes.join(", "),
+            issued.key
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only)]
+pub async fn revoke(
+    ctx: Context<'_>,
+    #[description = "ID of the key to revoke"] id: i32,
+) -> Result<(), Error> {
+    let message = if ctx.data().db.revoke_api_key(id).await? {
+        format!("✅ API key #{} was revoked", id)
+    } else {
+        format!("❌ No active API key #{}", id)
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let keys = ctx.data().db.list_api_keys().await?;
+
+    if keys.is_empty() {
+        ctx.send(|m| m.content("No API keys have been issued").ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("API keys");
+            // Embeds hold at most 25 fields, newest keys first
+            for key in keys.iter().rev().take(25) {
+                let last_used = key
+                    .last_used_at
+                    .map_or("Never".to_string(), |at| format!("<t:{}:R>", at.unix_timestamp()));
+                let status = key
+                    .revoked_at
+                    .map_or("Active".to_string(), |at| format!("Revoked <t:{}:R>", at.unix_timestamp()));
+                e.field(
+                    format!("#{} {}", key.id, key.name),
+                    format!(
+                        "`{}…`\nScopes: {}\nBy: <@{}>\nLast used: {}\nStatus: {}",
+                        key.key_prefix,
+                        key.scopes.join(", "),
+                        key.created_by,
+                        last_used,
+                        status
+                    ),
+                    false,
+                );
+            }
+            e
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use poise::serenity_prelude::User;
+use crate::app::{
+    commands::review::Data,
+    models::review::BlacklistScope,
+    utils::store::ReviewStore,
+};
+use time::{Duration, OffsetDateTime};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+#[poise::command(slash_command, owners_only, subcommands("add", "remove", "show"))]
+pub async fn blacklist(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use one of the subcommands: `/blacklist add`, `/blacklist remove` or `/blacklist show`").await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "User to blacklist"] user: User,
+    #[description = "What the user is blocked from"] scope: BlacklistScope,
+    #[description = "Why the user is blacklisted"] reason: Option<String>,
+    #[description = "Lift the entry after this many days"] days: Option<u32>,
+) -> Result<(), Error> {
+    let expires_at = days.map(|days| OffsetDateTime::now_utc() + Duration::days(days as i64));
+
+    ctx.data().db.blacklist_user(
+        user.id.0 as i64,
+        scope.clone(),
+        reason,
+        expires_at,
+        ctx.author().id.0 as i64,
+    ).await?;
+
+    let until = match days {
+        Some(days) => format!(" for {} day(s)", days),
+        None => String::new(),
+    };
+    ctx.send(|m| {
+        m.content(format!("✅ {} is blacklisted ({}){}", user.tag(), scope.name(), until))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "User to remove from the blacklist"] user: User,
+    #[description = "Entry to remove"] scope: BlacklistScope,
+) -> Result<(), Error> {
+    let removed = ctx.data().db.unblacklist_user(user.id.0 as i64, scope).await?;
+
+    let message = if removed {
+        format!("✅ {} was removed from the blacklist", user.tag())
+    } else {
+        format!("❌ {} has no such blacklist entry", user.tag())
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only)]
+pub async fn show(
+    ctx: Context<'_>,
+    #[description = "User to look up"] user: User,
+) -> Result<(), Error> {
+    let entries = ctx.data().db.get_active_blacklist(user.id.0 as i64).await?;
+
+    if entries.is_empty() {
+        ctx.send(|m| m.content(format!("{} is not blacklisted", user.tag())).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Blacklist: {}", user.tag()));
+            for entry in &entries {
+                let expires = entry
+                    .expires_at
+                    .map_or("Never".to_string(), |at| format!("<t:{}:R>", at.unix_timestamp()));
+                e.field(
+                    entry.scope.name(),
+                    format!(
+                        "Reason: {}\nBy: <@{}>\nExpires: {}",
+                        entry.reason.as_deref().unwrap_or("None given"),
+                        entry.created_by,
+                        expires
+                    ),
+                    false,
+                );
+            }
+            e
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
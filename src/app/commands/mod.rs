@@ -1 +1,4 @@
-pub mod review; 
\ No newline at end of file
+pub mod review; 
+pub mod blacklist;
+pub mod settings;
+pub mod apikey;
\ No newline at end of file
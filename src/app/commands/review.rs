@@ -1,201 +1,346 @@
-use poise::serenity_prelude::{self as serenity, User, CreateEmbed};
-use crate::app::{
-    models::review::{ReviewType, RatingCategory},
-    utils::{database::Database, colors::get_rating_color},
-};
-
-type Error = Box<dyn std::error::Error + Send + Sync>;
-type Context<'a> = poise::Context<'a, Data, Error>;
-
-#[derive(Clone)]
-pub struct Data {
-    pub db: Database,
-}
-
-#[poise::command(slash_command, subcommands("user", "server"))]
-pub async fn review(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.say("Please use one of the subcommands: `/review user` or `/review server`").await?;
-    Ok(())
-}
-
-#[poise::command(slash_command)]
-pub async fn user(
-    ctx: Context<'_>,
-    #[description = "User to review"] user: Option<User>,
-) -> Result<(), Error> {
-    let target_user = user.as_ref().unwrap_or(ctx.author());
-    handle_review(ctx, target_user, ReviewType::User).await
-}
-
-#[poise::command(slash_command)]
-pub async fn server(
-    ctx: Context<'_>,
-    #[description = "Server invite link"] invite: String,
-) -> Result<(), Error> {
-    let invite_code = invite
-        .split('/')
-        .last()
-        .ok_or("Invalid invite link")?;
-
-    let invite_info = ctx
-        .serenity_context()
-        .http
-        .get_invite(invite_code, false, false, None)
-        .await
-        .map_err(|_| "Invalid or expired invite")?;
-
-    let guild = invite_info.guild
-        .ok_or("Could not get server information")?;
-
-    handle_review(ctx, &guild, ReviewType::Server).await
-}
-
-async fn handle_review<T>(
-    ctx: Context<'_>,
-    target: &T,
-    review_type: ReviewType,
-) -> Result<(), Error>
-where
-    T: ReviewTarget,
-{
-    let target_id = target.get_id();
-    let average_rating = ctx.data().db.get_average_rating(target_id, &review_type).await?.unwrap_or(0.0);
-    let reviews_count = ctx.data().db.get_reviews_count(target_id, &review_type).await?;
-    let has_reviewed = ctx.data().db.has_reviewed(
-        target_id,
-        ctx.author().id.0 as i64,
-        &review_type
-    ).await?;
-
-    let category = RatingCategory::from_average(average_rating);
-
-    let mut embed = CreateEmbed::default();
-    target.build_embed(&mut embed, average_rating, &category, reviews_count);
-
-    if !target.is_self(ctx.author().id.0 as i64) {
-        let button_label = if has_reviewed {
-            "Update Review"
-        } else {
-            "Add Review"
-        };
-
-        let (review_button_id, reviews_list_id) = match review_type {
-            ReviewType::User => (
-                format!("review_button:{}", target_id),
-                format!("reviews_list:{}", target_id)
-            ),
-            ReviewType::Server => (
-                format!("server_review_button:{}", target_id),
-                format!("server_reviews_list:{}", target_id)
-            ),
-        };
-
-        ctx.send(|m| {
-            m.embed(|e| {
-                *e = embed;
-                e
-            })
-            .components(|c| {
-                c.create_action_row(|r| {
-                    r.create_button(|b| {
-                        b.custom_id(review_button_id)
-                            .label(button_label)
-                            .style(serenity::ButtonStyle::Primary)
-                    })
-                    .create_button(|b| {
-                        b.custom_id(reviews_list_id)
-                            .label("Reviews")
-                            .style(serenity::ButtonStyle::Secondary)
-                    })
-                })
-            })
-        })
-        .await?;
-    } else {
-        ctx.send(|m| {
-            m.embed(|e| {
-                *e = embed;
-                e
-            })
-        })
-        .await?;
-    }
-
-    Ok(())
-}
-
-trait ReviewTarget {
-    fn get_id(&self) -> i64;
-    fn is_self(&self, user_id: i64) -> bool;
-    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, category: &RatingCategory, reviews_count: i64);
-}
-
-impl ReviewTarget for User {
-    fn get_id(&self) -> i64 {
-        self.id.0 as i64
-    }
-
-    fn is_self(&self, user_id: i64) -> bool {
-        self.id.0 as i64 == user_id
-    }
-
-    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, category: &RatingCategory, reviews_count: i64) {
-        embed
-            .title(format!("User Review: {}", self.name))
-            .thumbnail(self.face())
-            .field("Rating", format!("{:.1} / 5.0", average_rating), true)
-            .field("Category", category.to_string(), true)
-            .field("Total Reviews", reviews_count.to_string(), true)
-            .color(get_rating_color(category));
-    }
-}
-
-impl ReviewTarget for serenity::Guild {
-    fn get_id(&self) -> i64 {
-        self.id.0 as i64
-    }
-
-    fn is_self(&self, _user_id: i64) -> bool {
-        false
-    }
-
-    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, category: &RatingCategory, reviews_count: i64) {
-        embed
-            .title(format!("Server Review: {}", self.name))
-            .thumbnail(self.icon_url().unwrap_or_default())
-            .field("Rating", format!("{:.1} / 5.0", average_rating), true)
-            .field("Category", category.to_string(), true)
-            .field("Total Reviews", reviews_count.to_string(), true)
-            .color(get_rating_color(category));
-    }
-}
-
-impl ReviewTarget for serenity::InviteGuild {
-    fn get_id(&self) -> i64 {
-        self.id.0 as i64
-    }
-
-    fn is_self(&self, _user_id: i64) -> bool {
-        false
-    }
-
-    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, category: &RatingCategory, reviews_count: i64) {
-        embed
-            .title(format!("Server Review: {}", self.name))
-            .field("Rating", format!("{:.1} / 5.0", average_rating), true)
-            .field("Category", category.to_string(), true)
-            .field("Total Reviews", reviews_count.to_string(), true)
-            .color(get_rating_color(category));
-
-        if let Some(icon) = &self.icon {
-            let icon_url = format!(
-                "https://cdn.discordapp.com/icons/{}/{}.{}",
-                self.id,
-                icon,
-                if icon.starts_with("a_") { "gif" } else { "png" }
-            );
-            embed.thumbnail(icon_url);
-        }
-    }
-}
+use poise::serenity_prelude::{self as serenity, User, CreateEmbed};
+use crate::app::{
+    models::review::{ReviewType, RatingCategory, RatingDistribution, ScoreView},
+    utils::{
+        database::Database, store::ReviewStore, colors::get_rating_color, moderation::ModerationPipeline,
+        rate_limit::RateLimiter, blacklist, scoring::ScoreConfig,
+    },
+};
+use std::sync::Arc;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Clone)]
+pub struct Data<S: ReviewStore = Database> {
+    pub db: S,
+    pub moderation: Arc<ModerationPipeline>,
+    pub rate_limiter: RateLimiter,
+    pub scoring: ScoreConfig,
+}
+
+#[poise::command(slash_command, subcommands("user", "server", "delete", "top"))]
+pub async fn review(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use one of the subcommands: `/review user`, `/review server`, `/review delete` or `/review top`").await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+pub async fn user(
+    ctx: Context<'_>,
+    #[description = "User to review"] user: Option<User>,
+) -> Result<(), Error> {
+    let target_user = user.as_ref().unwrap_or(ctx.author());
+    handle_review(ctx, target_user, ReviewType::User).await
+}
+
+#[poise::command(slash_command)]
+pub async fn server(
+    ctx: Context<'_>,
+    #[description = "Server invite link"] invite: String,
+) -> Result<(), Error> {
+    let guild = resolve_invite(ctx, &invite).await?;
+    handle_review(ctx, &guild, ReviewType::Server).await
+}
+
+#[poise::command(slash_command)]
+pub async fn delete(
+    ctx: Context<'_>,
+    #[description = "User whose review you want to delete"] user: Option<User>,
+    #[description = "Invite link of the server whose review you want to delete"] invite: Option<String>,
+) -> Result<(), Error> {
+    let (target_id, review_type) = match (user, invite) {
+        (Some(user), None) => (user.id.0 as i64, ReviewType::User),
+        (None, Some(invite)) => (resolve_invite(ctx, &invite).await?.id.0 as i64, ReviewType::Server),
+        _ => {
+            ctx.send(|m| m.content("❌ Provide either a user or a server invite").ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+
+    let author_id = ctx.author().id.0 as i64;
+    let deleted = ctx.data().db.delete_review(target_id, author_id, &review_type, author_id, None).await?;
+
+    let message = if deleted {
+        "✅ Your review was deleted"
+    } else {
+        "❌ You have not reviewed this target"
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+pub async fn top(
+    ctx: Context<'_>,
+    #[description = "Rank users or servers"] kind: ReviewType,
+) -> Result<(), Error> {
+    let ranked = ctx.data().scoring.leaderboard(&ctx.data().db, &kind, LEADERBOARD_SIZE).await?;
+
+    if ranked.is_empty() {
+        ctx.send(|m| m.content("❌ Nothing has been reviewed yet").ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = ranked
+        .iter()
+        .enumerate()
+        .map(|(i, (target, score))| {
+            let name = match kind {
+                ReviewType::User => format!("<@{}>", target.target_id),
+                ReviewType::Server => ctx
+                    .serenity_context()
+                    .cache
+                    .guild_field(target.target_id as u64, |g| g.name.clone())
+                    .unwrap_or_else(|| format!("Server {}", target.target_id)),
+            };
+            format!(
+                "**{}.** {} · score {:.1} (⭐ {:.1} from {} reviews)",
+                i + 1,
+                name,
+                score,
+                target.average,
+                target.review_count
+            )
+        })
+        .collect();
+
+    let title = match kind {
+        ReviewType::User => "Top Users",
+        ReviewType::Server => "Top Servers",
+    };
+    let top_score = ranked[0].1;
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(title)
+                .description(lines.join("\n"))
+                .color(get_rating_color(&RatingCategory::from_average(top_score)))
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn resolve_invite(ctx: Context<'_>, invite: &str) -> Result<serenity::InviteGuild, Error> {
+    let invite_code = invite
+        .split('/')
+        .next_back()
+        .ok_or("Invalid invite link")?;
+
+    let invite_info = ctx
+        .serenity_context()
+        .http
+        .get_invite(invite_code, false, false, None)
+        .await
+        .map_err(|_| "Invalid or expired invite")?;
+
+    Ok(invite_info.guild.ok_or("Could not get server information")?)
+}
+
+async fn handle_review<T>(
+    ctx: Context<'_>,
+    target: &T,
+    review_type: ReviewType,
+) -> Result<(), Error>
+where
+    T: ReviewTarget,
+{
+    let target_id = target.get_id();
+    let author_id = ctx.author().id.0 as i64;
+
+    if review_type == ReviewType::User && blacklist::is_hidden_everywhere(&ctx.data().db, target_id).await? {
+        ctx.send(|m| m.content("❌ This user's profile is not available").ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let denial = blacklist::review_denial(&ctx.data().db, author_id, target_id, &review_type).await?;
+    let average_rating = ctx.data().db.get_average_rating(target_id, &review_type).await?.unwrap_or(0.0);
+    let reviews_count = ctx.data().db.get_reviews_count(target_id, &review_type).await?;
+    let distribution = ctx.data().db.get_rating_distribution(target_id, &review_type).await?;
+    let has_reviewed = ctx.data().db.has_reviewed(target_id, author_id, &review_type).await?;
+
+    let criteria = ctx.data().db.get_criteria(&review_type, ctx.guild_id().map(|id| id.0 as i64)).await?;
+    let keys: Vec<String> = criteria.iter().map(|c| c.key.clone()).collect();
+    let criteria_averages = ctx.data().db.get_criteria_averages(target_id, &review_type, &keys).await?;
+
+    let view = match ctx.guild_id() {
+        Some(guild_id) => ctx.data().db.get_score_view(guild_id.0 as i64).await?.unwrap_or_default(),
+        None => ScoreView::default(),
+    };
+    let score = ctx.data().scoring.target_score(&ctx.data().db, target_id, &review_type, view, average_rating, reviews_count).await?;
+
+    // The category follows the score, so a handful of reviews cannot rank a target as Excellent
+    let category = RatingCategory::from_average(score);
+
+    let mut embed = CreateEmbed::default();
+    target.build_embed(&mut embed, average_rating, score, &ctx.data().scoring.view_label(view), &category, reviews_count);
+    for criterion in &criteria {
+        let value = match criteria_averages.get(&criterion.key) {
+            Some(average) => format!("{:.1} / 5.0", average),
+            None => "No ratings yet".to_string(),
+        };
+        embed.field(&criterion.label, value, true);
+    }
+    embed.field("Distribution", distribution_chart(&distribution), false);
+
+    if !target.is_self(author_id) {
+        let button_label = if has_reviewed {
+            "Update Review"
+        } else {
+            "Add Review"
+        };
+
+        let (review_button_id, reviews_list_id) = match review_type {
+            ReviewType::User => (
+                format!("review_button:{}", target_id),
+                format!("reviews_list:{}", target_id)
+            ),
+            ReviewType::Server => (
+                format!("server_review_button:{}", target_id),
+                format!("server_reviews_list:{}", target_id)
+            ),
+        };
+
+        ctx.send(|m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+            .components(|c| {
+                c.create_action_row(|r| {
+                    if denial.is_none() {
+                        r.create_button(|b| {
+                            b.custom_id(review_button_id)
+                                .label(button_label)
+                                .style(serenity::ButtonStyle::Primary)
+                        });
+                    }
+                    r.create_button(|b| {
+                        b.custom_id(reviews_list_id)
+                            .label("Reviews")
+                            .style(serenity::ButtonStyle::Secondary)
+                    })
+                })
+            })
+        })
+        .await?;
+    } else {
+        ctx.send(|m| {
+            m.embed(|e| {
+                *e = embed;
+                e
+            })
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+// One bar per star count, scaled to the share of all reviews
+fn distribution_chart(distribution: &RatingDistribution) -> String {
+    const WIDTH: i64 = 10;
+    let counts = distribution.counts();
+    let total: i64 = counts.iter().sum();
+
+    let rows: Vec<String> = counts
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, &count)| {
+            let filled = if total > 0 { (count * WIDTH + total / 2) / total } else { 0 };
+            format!(
+                "{} ★ {}{} {}",
+                index + 1,
+                "█".repeat(filled as usize),
+                "░".repeat((WIDTH - filled) as usize),
+                count
+            )
+        })
+        .collect();
+
+    format!("```\n{}\n```", rows.join("\n"))
+}
+
+trait ReviewTarget {
+    fn get_id(&self) -> i64;
+    fn is_self(&self, user_id: i64) -> bool;
+    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, score: f64, view_label: &str, category: &RatingCategory, reviews_count: i64);
+}
+
+impl ReviewTarget for User {
+    fn get_id(&self) -> i64 {
+        self.id.0 as i64
+    }
+
+    fn is_self(&self, user_id: i64) -> bool {
+        self.id.0 as i64 == user_id
+    }
+
+    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, score: f64, view_label: &str, category: &RatingCategory, reviews_count: i64) {
+        embed
+            .title(format!("User Review: {}", self.name))
+            .thumbnail(self.face())
+            .field("Rating", format!("{:.1} / 5.0", average_rating), true)
+            .field(format!("Score ({})", view_label), format!("{:.1} / 5.0", score), true)
+            .field("Category", category.to_string(), true)
+            .field("Total Reviews", reviews_count.to_string(), true)
+            .color(get_rating_color(category));
+    }
+}
+
+impl ReviewTarget for serenity::Guild {
+    fn get_id(&self) -> i64 {
+        self.id.0 as i64
+    }
+
+    fn is_self(&self, _user_id: i64) -> bool {
+        false
+    }
+
+    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, score: f64, view_label: &str, category: &RatingCategory, reviews_count: i64) {
+        embed
+            .title(format!("Server Review: {}", self.name))
+            .thumbnail(self.icon_url().unwrap_or_default())
+            .field("Rating", format!("{:.1} / 5.0", average_rating), true)
+            .field(format!("Score ({})", view_label), format!("{:.1} / 5.0", score), true)
+            .field("Category", category.to_string(), true)
+            .field("Total Reviews", reviews_count.to_string(), true)
+            .color(get_rating_color(category));
+    }
+}
+
+impl ReviewTarget for serenity::InviteGuild {
+    fn get_id(&self) -> i64 {
+        self.id.0 as i64
+    }
+
+    fn is_self(&self, _user_id: i64) -> bool {
+        false
+    }
+
+    fn build_embed(&self, embed: &mut CreateEmbed, average_rating: f64, score: f64, view_label: &str, category: &RatingCategory, reviews_count: i64) {
+        embed
+            .title(format!("Server Review: {}", self.name))
+            .field("Rating", format!("{:.1} / 5.0", average_rating), true)
+            .field(format!("Score ({})", view_label), format!("{:.1} / 5.0", score), true)
+            .field("Category", category.to_string(), true)
+            .field("Total Reviews", reviews_count.to_string(), true)
+            .color(get_rating_color(category));
+
+        if let Some(icon) = &self.icon {
+            let icon_url = format!(
+                "https://cdn.discordapp.com/icons/{}/{}.{}",
+                self.id,
+                icon,
+                if icon.starts_with("a_") { "gif" } else { "png" }
+            );
+            embed.thumbnail(icon_url);
+        }
+    }
+}
  
\ No newline at end of file
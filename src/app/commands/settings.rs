@@ -0,0 +1,94 @@
+use poise::serenity_prelude::Role;
+use crate::app::{
+    commands::review::Data,
+    handlers::button::MAX_MODAL_CRITERIA,
+    models::review::{RatingCriterion, ReviewType, ScoreView},
+    utils::store::ReviewStore,
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD", subcommands("score", "criteria", "reply_role"))]
+pub async fn settings(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use one of the subcommands: `/settings score`, `/settings criteria` or `/settings reply_role`").await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn score(
+    ctx: Context<'_>,
+    #[description = "Leave empty to see the current view"] view: Option<ScoreView>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("This command only works in servers")?.0 as i64;
+
+    let message = match view {
+        Some(view) => {
+            ctx.data().db.set_score_view(guild_id, view, ctx.author().id.0 as i64).await?;
+            format!("✅ Scores in this server now use {} ({})", view.name(), ctx.data().scoring.view_label(view))
+        }
+        None => {
+            let view = ctx.data().db.get_score_view(guild_id).await?.unwrap_or_default();
+            format!("Scores in this server use {} ({})", view.name(), ctx.data().scoring.view_label(view))
+        }
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn criteria(
+    ctx: Context<'_>,
+    #[description = "Reviews of users or servers"] kind: ReviewType,
+    #[description = "Comma separated aspects to rate, leave empty to go back to the defaults"] labels: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("This command only works in servers")?.0 as i64;
+
+    let mut criteria: Vec<RatingCriterion> = Vec::new();
+    for label in labels.as_deref().unwrap_or_default().split(',').filter(|l| !l.trim().is_empty()) {
+        let criterion = RatingCriterion::from_label(label);
+        if !criteria.iter().any(|c| c.key == criterion.key) {
+            criteria.push(criterion);
+        }
+    }
+
+    if criteria.len() > MAX_MODAL_CRITERIA || criteria.iter().any(|c| c.label.chars().count() > 32) {
+        ctx.send(|m| {
+            m.content(format!("❌ Pick at most {} aspects of up to 32 characters each", MAX_MODAL_CRITERIA))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.data().db.set_guild_criteria(&kind, guild_id, criteria).await?;
+    let current = ctx.data().db.get_criteria(&kind, Some(guild_id)).await?;
+    let labels: Vec<&str> = current.iter().map(|c| c.label.as_str()).collect();
+
+    ctx.send(|m| {
+        m.content(format!("✅ {} reviews in this server now rate: {}", kind.name(), labels.join(", ")))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn reply_role(
+    ctx: Context<'_>,
+    #[description = "Role allowed to answer reviews of this server, leave empty for the owner only"] role: Option<Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("This command only works in servers")?.0 as i64;
+
+    ctx.data().db.set_reply_role(guild_id, role.as_ref().map(|r| r.id.0 as i64), ctx.author().id.0 as i64).await?;
+
+    let message = match role {
+        Some(role) => format!("✅ Members with {} can now answer reviews of this server", role.name),
+        None => "✅ Only the server owner can answer reviews of this server now".to_string(),
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
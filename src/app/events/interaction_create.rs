@@ -1,23 +1,24 @@
 use poise::serenity_prelude::{Context, Interaction};
 use crate::app::{
+    commands::review::Data,
     handlers::{button, modal},
-    utils::database::Database,
+    utils::store::ReviewStore,
 };
 use tracing::error;
 
-pub async fn interaction_create(
+pub async fn interaction_create<S: ReviewStore>(
     ctx: Context,
     interaction: Interaction,
-    db: &Database,
+    data: &Data<S>,
 ) {
     match interaction {
         Interaction::MessageComponent(component) => {
-            if let Err(e) = button::handle_button(&ctx, &component, db).await {
+            if let Err(e) = button::handle_button(&ctx, &component, &data.db).await {
                 error!("Error handling button: {}", e);
             }
         }
         Interaction::ModalSubmit(modal_submit) => {
-            if let Err(e) = modal::handle_modal(&ctx, &modal_submit, db).await {
+            if let Err(e) = modal::handle_modal(&ctx, &modal_submit, data).await {
                 error!("Error handling modal: {}", e);
             }
         }
@@ -1,243 +1,629 @@
-use poise::serenity_prelude::{
-    Context,
-    MessageComponentInteraction,
-    CreateEmbed,
-    User,
-    InteractionResponseType,
-    InputTextStyle,
-    CreateComponents,
-    ButtonStyle,
-};
-use crate::app::{
-    utils::{database::Database, colors::get_rating_color},
-    models::review::{ReviewType, RatingCategory},
-};
-use time::format_description::well_known::Rfc3339;
-
-pub async fn handle_button(
-    ctx: &Context,
-    interaction: &MessageComponentInteraction,
-    db: &Database,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let custom_id = &interaction.data.custom_id;
-
-    match custom_id {
-        id if id.starts_with("review_button:") => {
-            create_review_modal(ctx, interaction, id, ReviewType::User).await?
-        }
-        id if id.starts_with("server_review_button:") => {
-            create_review_modal(ctx, interaction, id, ReviewType::Server).await?
-        }
-        id if id.starts_with("reviews_list:") => {
-            let target_id = id.strip_prefix("reviews_list:").unwrap().parse::<i64>()?;
-            show_reviews_page(ctx, interaction, db, target_id, 0, ReviewType::User).await?
-        }
-        id if id.starts_with("server_reviews_list:") => {
-            let target_id = id.strip_prefix("server_reviews_list:").unwrap().parse::<i64>()?;
-            show_reviews_page(ctx, interaction, db, target_id, 0, ReviewType::Server).await?
-        }
-        id if id.starts_with("reviews_page:") => {
-            handle_pagination(ctx, interaction, db, id).await?
-        }
-        _ => {}
-    }
-
-    Ok(())
-}
-
-async fn create_review_modal(
-    ctx: &Context,
-    interaction: &MessageComponentInteraction,
-    custom_id: &str,
-    review_type: ReviewType,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let id = custom_id.split(':').nth(1).unwrap();
-    let prefix = match review_type {
-        ReviewType::User => "review_modal:",
-        ReviewType::Server => "server_review_modal:",
-    };
-    let title = match review_type {
-        ReviewType::User => "User Review",
-        ReviewType::Server => "Server Review",
-    };
-    
-    interaction
-        .create_interaction_response(ctx, |r| {
-            r.kind(InteractionResponseType::Modal)
-                .interaction_response_data(|d| {
-                    d.custom_id(format!("{}{}", prefix, id))
-                        .title(format!("Write a {}", title))
-                        .components(|c| {
-                            c.create_action_row(|row| {
-                                row.create_input_text(|input| {
-                                    input
-                                        .custom_id("comment")
-                                        .label("Comment (optional)")
-                                        .style(InputTextStyle::Paragraph)
-                                        .required(false)
-                                        .placeholder("Write your review here...")
-                                })
-                            })
-                            .create_action_row(|row| {
-                                row.create_input_text(|input| {
-                                    input
-                                        .custom_id("rating")
-                                        .label("Rating (1-5)")
-                                        .style(InputTextStyle::Short)
-                                        .required(true)
-                                        .min_length(1)
-                                        .max_length(1)
-                                        .placeholder("Enter a number between 1 and 5")
-                                })
-                            })
-                        })
-                })
-        })
-        .await?;
-    Ok(())
-}
-
-async fn handle_pagination(
-    ctx: &Context,
-    interaction: &MessageComponentInteraction,
-    db: &Database,
-    custom_id: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let parts: Vec<&str> = custom_id.strip_prefix("reviews_page:").unwrap().split(':').collect();
-    if parts.len() == 3 {
-        let target_id = parts[0].parse::<i64>()?;
-        let page = parts[1].parse::<i64>()?;
-        let review_type = match parts[2] {
-            "user" => ReviewType::User,
-            "server" => ReviewType::Server,
-            _ => return Ok(()),
-        };
-        show_reviews_page(ctx, interaction, db, target_id, page, review_type).await?;
-    }
-    Ok(())
-}
-
-async fn show_reviews_page(
-    ctx: &Context,
-    interaction: &MessageComponentInteraction,
-    db: &Database,
-    target_id: i64,
-    page: i64,
-    review_type: ReviewType,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let total_reviews = db.get_reviews_count(target_id, &review_type).await?;
-
-    if total_reviews == 0 {
-        let message = match review_type {
-            ReviewType::User => "No reviews found for this user.",
-            ReviewType::Server => "No reviews found for this server.",
-        };
-        interaction
-            .create_interaction_response(ctx, |r| {
-                r.kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|d| d.content(message).ephemeral(true))
-            })
-            .await?;
-        return Ok(());
-    }
-
-    let reviews = db.get_paginated_reviews(target_id, page, 1, &review_type).await?;
-    
-    if let Some(review) = reviews.first() {
-        let reviewer = ctx.http.get_user(review.reviewer_id as u64).await?;
-        let timestamp = review.created_at
-            .map(|t| t.format(&Rfc3339).unwrap_or_else(|_| String::from("Unknown date")))
-            .unwrap_or_else(|| String::from("Unknown date"));
-        
-        let mut embed = CreateEmbed::default();
-        build_embed(&mut embed, &reviewer, review.rating, &review.comment, timestamp, page, total_reviews, &review_type, interaction, ctx).await?;
-
-        let type_str = match review_type {
-            ReviewType::User => "user",
-            ReviewType::Server => "server",
-        };
-
-        // TODO: Add a button to delete said review (Admin only)
-        // TODO: Add a button for all users to report the review
-        // TODO: Think about adding a dropdown for adding reactions to the review. Or maybe add an event listener for reactions and register them to the review.
-
-        let mut components = CreateComponents::default();
-        components.create_action_row(|row| {
-            row.create_button(|b| {
-                b.custom_id(format!("reviews_page:{}:{}:{}", target_id, page - 1, type_str))
-                    .label("Previous")
-                    .style(ButtonStyle::Secondary)
-                    .disabled(page <= 0)
-            })
-            .create_button(|b| {
-                b.custom_id(format!("reviews_page:{}:{}:{}", target_id, page + 1, type_str))
-                    .label("Next")
-                    .style(ButtonStyle::Secondary)
-                    .disabled(page >= total_reviews - 1)
-            })
-        });
-
-        interaction
-            .create_interaction_response(ctx, |r| {
-                r.kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|d| {
-                        d.embed(|e| {
-                            *e = embed;
-                            e
-                        })
-                        .components(|c| {
-                            *c = components;
-                            c
-                        })
-                        .ephemeral(true)
-                    })
-            })
-            .await?;
-    }
-
-    Ok(())
-}
-
-async fn build_embed(
-    embed: &mut CreateEmbed,
-    reviewer: &User,
-    rating: i32,
-    comment: &Option<String>,
-    timestamp: String,
-    page: i64,
-    total_reviews: i64,
-    review_type: &ReviewType,
-    interaction: &MessageComponentInteraction,
-    ctx: &Context,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let star_rating = "⭐".repeat(rating as usize);
-    let rating_category = RatingCategory::from_average(rating as f64);
-    
-    embed
-        .color(get_rating_color(&rating_category))
-        .author(|a| a.name(&reviewer.name).icon_url(reviewer.face()))
-        .title(format!("{} {} of {}", 
-            match review_type {
-                ReviewType::User => "Review",
-                ReviewType::Server => "Server Review",
-            },
-            page + 1, 
-            total_reviews
-        ))
-        .description(format!("{}", 
-            comment.as_deref().unwrap_or("*No comment provided*")
-        ))
-        .field("Rating", format!("{} ({})", star_rating, rating), true)
-        .field("Reviewer", format!("<@{}>", reviewer.id), true);
-
-    if let ReviewType::Server = review_type {
-        if let Some(guild) = interaction.guild_id {
-            embed.field("Server", guild.name(ctx).unwrap_or_else(|| "Unknown Server".to_string()), true);
-        }
-    }
-
-    // TODO: humanize the timestamp
-
-    embed.footer(|f| f.text(format!("Posted on {}", timestamp)));
-    Ok(())
+use poise::serenity_prelude::{
+    Context,
+    MessageComponentInteraction,
+    CreateEmbed,
+    User,
+    InteractionResponseType,
+    InputTextStyle,
+    CreateComponents,
+    ButtonStyle,
+};
+use crate::app::{
+    handlers::moderation,
+    utils::{
+        store::ReviewStore, colors::get_rating_color, permissions::{self, can_delete_review}, blacklist, validation,
+    },
+    models::review::{Review, ReviewType, RatingCategory, ReviewSort, ReviewQuery},
+};
+use std::env;
+use time::format_description::well_known::Rfc3339;
+
+// Modals hold five inputs and the comment and rating take two of them
+pub const MAX_MODAL_CRITERIA: usize = 3;
+const DEFAULT_REACTIONS: &str = "👍,❤️,😂,😮,😢";
+
+pub async fn handle_button<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let custom_id = &interaction.data.custom_id;
+
+    match custom_id {
+        id if id.starts_with("review_button:") => {
+            create_review_modal(ctx, interaction, db, id, ReviewType::User).await?
+        }
+        id if id.starts_with("server_review_button:") => {
+            create_review_modal(ctx, interaction, db, id, ReviewType::Server).await?
+        }
+        id if id.starts_with("reviews_list:") => {
+            let target_id = id.strip_prefix("reviews_list:").unwrap().parse::<i64>()?;
+            show_reviews_page(ctx, interaction, db, target_id, 0, ReviewType::User, ReviewSort::Newest).await?
+        }
+        id if id.starts_with("server_reviews_list:") => {
+            let target_id = id.strip_prefix("server_reviews_list:").unwrap().parse::<i64>()?;
+            show_reviews_page(ctx, interaction, db, target_id, 0, ReviewType::Server, ReviewSort::Newest).await?
+        }
+        id if id.starts_with("reviews_page:") => {
+            handle_pagination(ctx, interaction, db, id).await?
+        }
+        id if id.starts_with("review_vote:") => {
+            handle_vote(ctx, interaction, db, id).await?
+        }
+        id if id.starts_with("review_react:") => {
+            handle_reaction(ctx, interaction, db, id).await?
+        }
+        id if id.starts_with("review_delete:") => {
+            create_delete_modal(ctx, interaction, id).await?
+        }
+        id if id.starts_with("review_report:") => {
+            create_report_modal(ctx, interaction, id).await?
+        }
+        id if id.starts_with("review_reply:") => {
+            create_reply_modal(ctx, interaction, db, id).await?
+        }
+        id if id.starts_with("report_resolve:") => {
+            moderation::handle_resolution(ctx, interaction, db, id).await?
+        }
+        id if id.starts_with("pending_resolve:") => {
+            moderation::handle_pending_resolution(ctx, interaction, db, id).await?
+        }
+        id if id.starts_with("flag_clear:") => {
+            moderation::handle_flag_clear(ctx, interaction, db, id).await?
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+async fn create_review_modal<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+    review_type: ReviewType,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let id = custom_id.split(':').nth(1).unwrap();
+    let target_id = id.parse::<i64>()?;
+
+    if let Some(denial) = blacklist::review_denial(db, interaction.user.id.0 as i64, target_id, &review_type).await? {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content(format!("❌ {}", denial))
+                            .ephemeral(true)
+                    })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let prefix = match review_type {
+        ReviewType::User => "review_modal:",
+        ReviewType::Server => "server_review_modal:",
+    };
+    let title = match review_type {
+        ReviewType::User => "User Review",
+        ReviewType::Server => "Server Review",
+    };
+
+    let guild_id = interaction.guild_id.map(|id| id.0 as i64);
+    let mut criteria = db.get_criteria(&review_type, guild_id).await?;
+    criteria.truncate(MAX_MODAL_CRITERIA);
+    
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("{}{}", prefix, id))
+                        .title(format!("Write a {}", title))
+                        .components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_input_text(|input| {
+                                    input
+                                        .custom_id("comment")
+                                        .label("Comment (optional)")
+                                        .style(InputTextStyle::Paragraph)
+                                        .required(false)
+                                        .max_length(validation::MAX_COMMENT_LENGTH as u64)
+                                        .placeholder("Write your review here...")
+                                })
+                            })
+                            .create_action_row(|row| {
+                                row.create_input_text(|input| {
+                                    input
+                                        .custom_id("rating")
+                                        .label("Rating (1-5)")
+                                        .style(InputTextStyle::Short)
+                                        .required(true)
+                                        .min_length(1)
+                                        .max_length(1)
+                                        .placeholder("Enter a number between 1 and 5")
+                                })
+                            });
+
+                            for criterion in &criteria {
+                                c.create_action_row(|row| {
+                                    row.create_input_text(|input| {
+                                        input
+                                            .custom_id(format!("criterion:{}", criterion.key))
+                                            .label(format!("{} (1-5, optional)", criterion.label))
+                                            .style(InputTextStyle::Short)
+                                            .required(false)
+                                            .max_length(1)
+                                    })
+                                });
+                            }
+                            c
+                        })
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+async fn create_delete_modal(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let key = custom_id.strip_prefix("review_delete:").unwrap();
+    let reviewer_id = key
+        .split(':')
+        .nth(1)
+        .ok_or("Malformed delete button")?
+        .parse::<i64>()?;
+
+    if !can_delete_review(interaction.guild_id, interaction.member.as_ref(), interaction.user.id.0 as i64, reviewer_id) {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content("❌ You are not allowed to delete this review")
+                            .ephemeral(true)
+                    })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("review_delete_modal:{}", key))
+                        .title("Delete Review")
+                        .components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_input_text(|input| {
+                                    input
+                                        .custom_id("reason")
+                                        .label("Reason (optional)")
+                                        .style(InputTextStyle::Paragraph)
+                                        .required(false)
+                                        .max_length(500)
+                                        .placeholder("Why is this review being removed?")
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+async fn create_report_modal(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let review_id = custom_id.strip_prefix("review_report:").unwrap().parse::<i32>()?;
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("review_report_modal:{}", review_id))
+                        .title("Report Review")
+                        .components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_input_text(|input| {
+                                    input
+                                        .custom_id("reason")
+                                        .label("Reason")
+                                        .style(InputTextStyle::Paragraph)
+                                        .required(true)
+                                        .min_length(3)
+                                        .max_length(500)
+                                        .placeholder("What is wrong with this review?")
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+async fn create_reply_modal<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let review_id = custom_id.strip_prefix("review_reply:").unwrap().parse::<i32>()?;
+
+    let review = match db.get_review_by_id(review_id).await? {
+        Some(review) => review,
+        None => {
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| d.content("❌ This review no longer exists").ephemeral(true))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let user_id = interaction.user.id.0 as i64;
+    if !permissions::can_reply(ctx, db, interaction.guild_id, interaction.member.as_ref(), user_id, &review).await? {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content("❌ You cannot reply to this review").ephemeral(true))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("review_reply_modal:{}", review_id))
+                        .title("Reply to Review")
+                        .components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_input_text(|input| {
+                                    input
+                                        .custom_id("reply")
+                                        .label("Reply (leave empty to remove it)")
+                                        .style(InputTextStyle::Paragraph)
+                                        .required(false)
+                                        .max_length(1000)
+                                        .value(review.reply.clone().unwrap_or_default())
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+async fn handle_pagination<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parts: Vec<&str> = custom_id.strip_prefix("reviews_page:").unwrap().split(':').collect();
+    if parts.len() == 3 || parts.len() == 4 {
+        let target_id = parts[0].parse::<i64>()?;
+        let page = parts[1].parse::<i64>()?;
+        let review_type = match parts[2] {
+            "user" => ReviewType::User,
+            "server" => ReviewType::Server,
+            _ => return Ok(()),
+        };
+        // Pages sent before sorting existed have no sort part
+        let sort = parts.get(3).and_then(|s| ReviewSort::parse(s)).unwrap_or_default();
+        show_reviews_page(ctx, interaction, db, target_id, page, review_type, sort).await?;
+    }
+    Ok(())
+}
+
+async fn show_reviews_page<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    target_id: i64,
+    page: i64,
+    review_type: ReviewType,
+    sort: ReviewSort,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let total_reviews = db.get_reviews_count(target_id, &review_type).await?;
+
+    if total_reviews == 0 {
+        let message = match review_type {
+            ReviewType::User => "No reviews found for this user.",
+            ReviewType::Server => "No reviews found for this server.",
+        };
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content(message).ephemeral(true))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let reviews = db.get_paginated_reviews(target_id, page, 1, &review_type, &ReviewQuery::sorted(sort)).await?;
+    
+    if let Some(review) = reviews.first() {
+        let reviewer = ctx.http.get_user(review.reviewer_id as u64).await?;
+        let reactions = db.get_review_reactions(review.id).await?;
+        
+        let mut embed = CreateEmbed::default();
+        let votes = vote_summary(review, &reactions);
+        build_embed(&mut embed, &reviewer, review, votes, page, total_reviews, &review_type, interaction, ctx).await?;
+
+        let type_str = match review_type {
+            ReviewType::User => "user",
+            ReviewType::Server => "server",
+        };
+
+        let other_sort = sort.next();
+
+        let mut components = CreateComponents::default();
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.custom_id(format!("reviews_page:{}:{}:{}:{}", target_id, page - 1, type_str, sort.as_str()))
+                    .label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page <= 0)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("reviews_page:{}:{}:{}:{}", target_id, page + 1, type_str, sort.as_str()))
+                    .label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page >= total_reviews - 1)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("reviews_page:{}:0:{}:{}", target_id, type_str, other_sort.as_str()))
+                    .label(other_sort.label())
+                    .style(ButtonStyle::Secondary)
+            })
+        });
+
+        let can_delete = can_delete_review(
+            interaction.guild_id,
+            interaction.member.as_ref(),
+            interaction.user.id.0 as i64,
+            review.reviewer_id,
+        );
+        let can_reply = permissions::can_reply(
+            ctx,
+            db,
+            interaction.guild_id,
+            interaction.member.as_ref(),
+            interaction.user.id.0 as i64,
+            review,
+        ).await?;
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.custom_id(format!("review_vote:{}:helpful", review.id))
+                    .label("Helpful")
+                    .emoji('👍')
+                    .style(ButtonStyle::Success)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("review_vote:{}:unhelpful", review.id))
+                    .label("Not helpful")
+                    .emoji('👎')
+                    .style(ButtonStyle::Secondary)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("review_report:{}", review.id))
+                    .label("Report")
+                    .style(ButtonStyle::Secondary)
+            });
+            if can_delete {
+                row.create_button(|b| {
+                    b.custom_id(format!("review_delete:{}:{}:{}", target_id, review.reviewer_id, type_str))
+                        .label("Delete")
+                        .style(ButtonStyle::Danger)
+                });
+            }
+            if can_reply {
+                row.create_button(|b| {
+                    b.custom_id(format!("review_reply:{}", review.id))
+                        .label(if review.reply.is_some() { "Edit reply" } else { "Reply" })
+                        .style(ButtonStyle::Primary)
+                });
+            }
+            row
+        });
+
+        let choices = reaction_choices();
+        if !choices.is_empty() {
+            components.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id(format!("review_react:{}", review.id))
+                        .placeholder("React to this review")
+                        .options(|options| {
+                            for emoji in &choices {
+                                options.create_option(|o| o.label(emoji).value(emoji));
+                            }
+                            options
+                        })
+                })
+            });
+        }
+
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.embed(|e| {
+                            *e = embed;
+                            e
+                        })
+                        .components(|c| {
+                            *c = components;
+                            c
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Emoji offered in the reaction menu, an empty REVIEW_REACTIONS turns the menu off
+fn reaction_choices() -> Vec<String> {
+    env::var("REVIEW_REACTIONS")
+        .unwrap_or_else(|_| DEFAULT_REACTIONS.to_string())
+        .split(',')
+        .map(|emoji| emoji.trim().to_string())
+        .filter(|emoji| !emoji.is_empty())
+        .take(25)
+        .collect()
+}
+
+fn vote_summary(review: &Review, reactions: &[(String, i64)]) -> String {
+    let mut summary = format!("👍 {} · 👎 {}", review.helpful_votes, review.unhelpful_votes);
+
+    if !reactions.is_empty() {
+        let counts: Vec<String> = reactions
+            .iter()
+            .map(|(emoji, count)| format!("{} {}", emoji, count))
+            .collect();
+        summary.push_str(&format!(" • {}", counts.join(" ")));
+    }
+
+    summary
+}
+
+async fn handle_vote<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parts: Vec<&str> = custom_id.strip_prefix("review_vote:").unwrap().split(':').collect();
+    if parts.len() != 2 {
+        return Ok(());
+    }
+    let review_id = parts[0].parse::<i32>()?;
+    let helpful = parts[1] == "helpful";
+    let voter_id = interaction.user.id.0 as i64;
+
+    let message = match db.get_review_by_id(review_id).await? {
+        Some(review) if review.reviewer_id == voter_id => "❌ You cannot vote on your own review".to_string(),
+        Some(_) => match db.vote_review(review_id, voter_id, helpful).await? {
+            Some(tally) => {
+                let action = match tally.vote {
+                    Some(true) => "✅ Marked as helpful",
+                    Some(false) => "✅ Marked as not helpful",
+                    None => "✅ Vote removed",
+                };
+                format!("{} (👍 {} · 👎 {})", action, tally.helpful_votes, tally.unhelpful_votes)
+            }
+            None => "❌ This review no longer exists".to_string(),
+        },
+        None => "❌ This review no longer exists".to_string(),
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(message).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_reaction<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let review_id = custom_id.strip_prefix("review_react:").unwrap().parse::<i32>()?;
+
+    // Only emoji from the menu are stored, in case the list changed since it was sent
+    let emoji = match interaction.data.values.first() {
+        Some(emoji) if reaction_choices().contains(emoji) => emoji.clone(),
+        _ => return Ok(()),
+    };
+
+    let message = if db.react_to_review(review_id, interaction.user.id.0 as i64, emoji.clone()).await? {
+        format!("✅ You reacted with {}", emoji)
+    } else {
+        "❌ This review no longer exists".to_string()
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(message).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn build_embed(
+    embed: &mut CreateEmbed,
+    reviewer: &User,
+    review: &Review,
+    votes: String,
+    page: i64,
+    total_reviews: i64,
+    review_type: &ReviewType,
+    interaction: &MessageComponentInteraction,
+    ctx: &Context,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let rating = review.rating;
+    let star_rating = "⭐".repeat(rating as usize);
+    let rating_category = RatingCategory::from_average(rating as f64);
+    let timestamp = review.created_at
+        .map(|t| t.format(&Rfc3339).unwrap_or_else(|_| String::from("Unknown date")))
+        .unwrap_or_else(|| String::from("Unknown date"));
+    let edited = review.updated_at
+        .map(|t| t.format(&Rfc3339).unwrap_or_else(|_| String::from("Unknown date")));
+
+    let mut description = review.comment.clone().unwrap_or_else(|| "*No comment provided*".to_string());
+    if let (Some(reply), Some(reply_by)) = (&review.reply, review.reply_by) {
+        description.push_str(&format!("\n\n**Reply from <@{}>**\n{}", reply_by, reply));
+    }
+    
+    embed
+        .color(get_rating_color(&rating_category))
+        .author(|a| a.name(&reviewer.name).icon_url(reviewer.face()))
+        .title(format!("{} {} of {}", 
+            match review_type {
+                ReviewType::User => "Review",
+                ReviewType::Server => "Server Review",
+            },
+            page + 1, 
+            total_reviews
+        ))
+        .description(description)
+        .field("Rating", format!("{} ({})", star_rating, rating), true)
+        .field("Reviewer", format!("<@{}>", reviewer.id), true);
+
+    if let ReviewType::Server = review_type {
+        if let Some(guild) = interaction.guild_id {
+            embed.field("Server", guild.name(ctx).unwrap_or_else(|| "Unknown Server".to_string()), true);
+        }
+    }
+
+    // TODO: humanize the timestamp
+
+    let footer = match edited {
+        Some(edited) => format!("Posted on {} • Edited on {} • {}", timestamp, edited, votes),
+        None => format!("Posted on {} • {}", timestamp, votes),
+    };
+
+    embed.footer(|f| f.text(footer));
+    Ok(())
 }
\ No newline at end of file
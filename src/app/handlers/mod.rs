@@ -1,2 +1,3 @@
-pub mod button;
-pub mod modal; 
\ No newline at end of file
+pub mod button;
+pub mod modal;
+pub mod moderation;
\ No newline at end of file
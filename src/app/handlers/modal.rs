@@ -1,110 +1,514 @@
-use poise::serenity_prelude::{
-    Context,
-    ModalSubmitInteraction,
-    InteractionResponseType,
-};
-use crate::app::{
-    utils::database::Database,
-    models::review::ReviewType,
-};
-
-pub async fn handle_modal(
-    ctx: &Context,
-    interaction: &ModalSubmitInteraction,
-    db: &Database,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let custom_id = &interaction.data.custom_id;
-    let (target_id, review_type) = if let Some(id) = custom_id.strip_prefix("review_modal:") {
-        (id.parse::<i64>()?, ReviewType::User)
-    } else if let Some(id) = custom_id.strip_prefix("server_review_modal:") {
-        (id.parse::<i64>()?, ReviewType::Server)
-    } else {
-        return Ok(());
-    };
-
-    let rating_str = interaction
-        .data
-        .components
-        .get(1)
-        .and_then(|row| row.components.first())
-        .and_then(|component| match component {
-            poise::serenity_prelude::ActionRowComponent::InputText(input) => Some(input.value.clone()),
-            _ => None
-        })
-        .ok_or("Could not find rating input")?;
-
-    let rating = match rating_str.parse::<i32>() {
-        Ok(r) if r >= 1 && r <= 5 => r,
-        _ => {
-            interaction
-                .create_interaction_response(ctx, |r| {
-                    r.kind(InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|d| {
-                            d.content("❌ Rating must be a number between 1 and 5")
-                                .ephemeral(true)
-                        })
-                })
-                .await?;
-            return Ok(());
-        }
-    };
-
-    let comment = interaction
-        .data
-        .components
-        .get(0)
-        .and_then(|row| row.components.first())
-        .and_then(|component| match component {
-            poise::serenity_prelude::ActionRowComponent::InputText(input) => {
-                if input.value.trim().is_empty() {
-                    None
-                } else {
-                    Some(input.value.clone())
-                }
-            },
-            _ => None
-        });
-
-    let has_reviewed = db.has_reviewed(target_id, interaction.user.id.0 as i64, &review_type).await?;
-    
-    if has_reviewed {
-        db.update_review(
-            target_id,
-            interaction.user.id.0 as i64,
-            rating,
-            comment,
-            &review_type,
-        ).await?;
-    } else {
-        db.add_review(
-            target_id,
-            interaction.user.id.0 as i64,
-            rating,
-            comment,
-            &review_type,
-        ).await?;
-    }
-
-    // TODO: Add moderation such as text moderation (highly likely the moderation AI from the OpenAI API)
-    // TODO: Add support for images in the review (will probably use self hosted image moderation AI)
-
-    let _ = db.get_average_rating(target_id, &review_type).await;
-
-    let message = if has_reviewed {
-        "✅ Review updated successfully!"
-    } else {
-        "✅ Review submitted successfully!"
-    };
-
-    interaction
-        .create_interaction_response(ctx, |r| {
-            r.kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|d| {
-                    d.content(message)
-                        .ephemeral(true)
-                })
-        })
-        .await?;
-
-    Ok(())
-}
\ No newline at end of file
+use poise::serenity_prelude::{
+    Context,
+    ModalSubmitInteraction,
+    InteractionResponseType,
+};
+use crate::app::{
+    commands::review::Data,
+    handlers::moderation,
+    utils::{
+        store::ReviewStore, permissions::{can_delete_review, can_reply}, moderation::Verdict, rate_limit, blacklist,
+        validation::{self, ReviewDraft, ValidReview},
+    },
+    models::review::{Review, ReviewStatus, ReviewType, RatingCriterion},
+};
+use std::collections::BTreeMap;
+
+pub async fn handle_modal<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &ModalSubmitInteraction,
+    data: &Data<S>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let db = &data.db;
+    let custom_id = &interaction.data.custom_id;
+
+    if let Some(key) = custom_id.strip_prefix("review_delete_modal:") {
+        return handle_delete_modal(ctx, interaction, db, key).await;
+    }
+
+    if let Some(id) = custom_id.strip_prefix("review_report_modal:") {
+        return handle_report_modal(ctx, interaction, db, id.parse::<i32>()?).await;
+    }
+
+    if let Some(id) = custom_id.strip_prefix("review_reply_modal:") {
+        return handle_reply_modal(ctx, interaction, data, id.parse::<i32>()?).await;
+    }
+
+    let (target_id, review_type) = if let Some(id) = custom_id.strip_prefix("review_modal:") {
+        (id.parse::<i64>()?, ReviewType::User)
+    } else if let Some(id) = custom_id.strip_prefix("server_review_modal:") {
+        (id.parse::<i64>()?, ReviewType::Server)
+    } else {
+        return Ok(());
+    };
+
+    let inputs: Vec<(&str, &str)> = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .filter_map(|component| match component {
+            poise::serenity_prelude::ActionRowComponent::InputText(input) => {
+                Some((input.custom_id.as_str(), input.value.as_str()))
+            }
+            _ => None
+        })
+        .collect();
+    // The comment is the first input and the rating the second, criteria follow
+    let rating = inputs.get(1).map(|(_, value)| *value).ok_or("Could not find rating input")?;
+
+    let form = ReviewForm {
+        target_id,
+        reviewer_id: interaction.user.id.0 as i64,
+        review_type,
+        guild_id: interaction.guild_id.map(|id| id.0 as i64),
+        rating,
+        comment: inputs.first().map(|(_, value)| *value),
+        inputs: &inputs,
+    };
+    let submission = submit_review(data, form).await?;
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(submission.message())
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+
+    if let Submission::Saved { review, held: Some(reason), .. } = &submission {
+        moderation::post_pending_review(&ctx.http, review, reason).await?;
+    }
+
+    Ok(())
+}
+
+// The inputs of a review modal, apart from the interaction they came with
+struct ReviewForm<'a> {
+    target_id: i64,
+    reviewer_id: i64,
+    review_type: ReviewType,
+    guild_id: Option<i64>,
+    rating: &'a str,
+    comment: Option<&'a str>,
+    // Every text input as (custom_id, value), criteria are picked out of these
+    inputs: &'a [(&'a str, &'a str)],
+}
+
+enum Submission {
+    Refused(String),
+    Saved {
+        review: Review,
+        created: bool,
+        held: Option<String>,
+    },
+}
+
+impl Submission {
+    fn message(&self) -> String {
+        match self {
+            Submission::Refused(message) => message.clone(),
+            Submission::Saved { held: Some(_), .. } => {
+                "⏳ Your review was received and will be published once a moderator approves it".to_string()
+            }
+            Submission::Saved { created: true, .. } => "✅ Review submitted successfully!".to_string(),
+            Submission::Saved { .. } => "✅ Review updated successfully!".to_string(),
+        }
+    }
+}
+
+async fn submit_review<S: ReviewStore>(
+    data: &Data<S>,
+    form: ReviewForm<'_>,
+) -> Result<Submission, Box<dyn std::error::Error + Send + Sync>> {
+    let db = &data.db;
+    let ReviewForm { target_id, reviewer_id, review_type, .. } = form;
+
+    // Checked again on submit, an entry may have been added while the modal was open
+    if let Some(denial) = blacklist::review_denial(db, reviewer_id, target_id, &review_type).await? {
+        return Ok(Submission::Refused(format!("❌ {}", denial)));
+    }
+
+    let definitions = db.get_criteria(&review_type, form.guild_id).await?;
+    let draft = ReviewDraft {
+        review_type: review_type.as_str(),
+        target_id,
+        reviewer_id,
+        rating: form.rating.trim().parse::<i32>().ok(),
+        comment: form.comment,
+        criteria: parse_criteria(form.inputs, &definitions),
+    };
+    let ValidReview { rating, comment, criteria, .. } = match validation::validate_review(draft, &definitions) {
+        Ok(review) => review,
+        Err(errors) => {
+            let message = errors
+                .0
+                .iter()
+                .map(|e| format!("❌ {}", e.message))
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Ok(Submission::Refused(message));
+        }
+    };
+
+    let (status, held) = match data.moderation.review(comment.as_deref()) {
+        Verdict::Allow => (ReviewStatus::Published, None),
+        Verdict::Hold(reason) => (ReviewStatus::Pending, Some(reason)),
+        Verdict::Reject(reason) => {
+            return Ok(Submission::Refused(format!("❌ Your review was rejected: the comment {}", reason)));
+        }
+    };
+
+    // Taken last, so only a review that is about to be saved uses up a slot
+    let editing = db.has_reviewed(target_id, reviewer_id, &review_type).await?;
+    if let Some(wait) = data.rate_limiter.acquire(reviewer_id, target_id, &review_type, editing).await {
+        return Ok(Submission::Refused(format!(
+            "⏳ You are reviewing too quickly, try again in {}",
+            rate_limit::format_wait(wait)
+        )));
+    }
+
+    let (review, created) = db.upsert_review(
+        target_id,
+        reviewer_id,
+        rating,
+        comment,
+        criteria,
+        &review_type,
+        status,
+    ).await?;
+
+    // TODO: Add support for images in the review (will probably use self hosted image moderation AI)
+
+    Ok(Submission::Saved { review, created, held })
+}
+
+// Criterion inputs left empty are skipped, ones that are not a number are kept as None
+fn parse_criteria(inputs: &[(&str, &str)], definitions: &[RatingCriterion]) -> BTreeMap<String, Option<i32>> {
+    let mut criteria = BTreeMap::new();
+
+    for (custom_id, value) in inputs {
+        // A criterion removed while the modal was open is dropped
+        let key = match custom_id.strip_prefix("criterion:") {
+            Some(key) if definitions.iter().any(|c| c.key == key) => key,
+            _ => continue,
+        };
+
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        criteria.insert(key.to_string(), value.parse::<i32>().ok());
+    }
+
+    criteria
+}
+
+async fn handle_delete_modal<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &ModalSubmitInteraction,
+    db: &S,
+    key: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parts: Vec<&str> = key.split(':').collect();
+    if parts.len() != 3 {
+        return Ok(());
+    }
+
+    let target_id = parts[0].parse::<i64>()?;
+    let reviewer_id = parts[1].parse::<i64>()?;
+    let review_type = match ReviewType::parse(parts[2]) {
+        Some(review_type) => review_type,
+        None => return Ok(()),
+    };
+    let user_id = interaction.user.id.0 as i64;
+
+    let message = if !can_delete_review(interaction.guild_id, interaction.member.as_ref(), user_id, reviewer_id) {
+        "❌ You are not allowed to delete this review"
+    } else {
+        let reason = interaction
+            .data
+            .components
+            .first()
+            .and_then(|row| row.components.first())
+            .and_then(|component| match component {
+                poise::serenity_prelude::ActionRowComponent::InputText(input) if !input.value.trim().is_empty() => {
+                    Some(input.value.trim().to_string())
+                },
+                _ => None
+            });
+
+        if db.delete_review(target_id, reviewer_id, &review_type, user_id, reason).await? {
+            "✅ Review deleted"
+        } else {
+            "❌ This review no longer exists"
+        }
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(message)
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_reply_modal<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &ModalSubmitInteraction,
+    data: &Data<S>,
+    review_id: i32,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let db = &data.db;
+    let user_id = interaction.user.id.0 as i64;
+
+    let reply = interaction
+        .data
+        .components
+        .first()
+        .and_then(|row| row.components.first())
+        .and_then(|component| match component {
+            poise::serenity_prelude::ActionRowComponent::InputText(input) => validation::clean_comment(&input.value),
+            _ => None
+        });
+
+    // Checked again on submit, the role may have been taken away while the modal was open
+    let message = match db.get_review_by_id(review_id).await? {
+        None => "❌ This review no longer exists".to_string(),
+        Some(review) if !can_reply(ctx, db, interaction.guild_id, interaction.member.as_ref(), user_id, &review).await? => {
+            "❌ You cannot reply to this review".to_string()
+        }
+        Some(_) => match reply.as_deref().map(|reply| data.moderation.review(Some(reply))) {
+            Some(Verdict::Hold(reason)) | Some(Verdict::Reject(reason)) => {
+                format!("❌ Your reply was rejected: it {}", reason)
+            }
+            _ => {
+                let removed = reply.is_none();
+                match db.reply_to_review(review_id, user_id, reply).await? {
+                    Some(_) if removed => "✅ Your reply was removed".to_string(),
+                    Some(_) => "✅ Your reply was posted".to_string(),
+                    None => "❌ This review no longer exists".to_string(),
+                }
+            }
+        },
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(message)
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_report_modal<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &ModalSubmitInteraction,
+    db: &S,
+    review_id: i32,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let reason = interaction
+        .data
+        .components
+        .first()
+        .and_then(|row| row.components.first())
+        .and_then(|component| match component {
+            poise::serenity_prelude::ActionRowComponent::InputText(input) => Some(input.value.trim().to_string()),
+            _ => None
+        })
+        .ok_or("Could not find reason input")?;
+
+    let report = db.report_review(review_id, interaction.user.id.0 as i64, reason).await?;
+
+    let message = if report.is_some() {
+        "✅ Thanks, the review was reported to the moderators"
+    } else {
+        "❌ You already reported this review, or it is no longer available"
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(message)
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+
+    if let Some(report) = report {
+        moderation::post_report(&ctx.http, db, &report).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use time::Duration;
+    use crate::app::{
+        models::review::BlacklistScope,
+        utils::{
+            cache::NoCache,
+            memory_cache::MemoryCache,
+            memory_store::MemoryStore,
+            moderation::{ModerationPipeline, WordListCheck},
+            rate_limit::{RateLimiter, RateLimitRules},
+            scoring::{ScoreConfig, ScoringMode},
+        },
+    };
+
+    const TARGET: i64 = 10;
+    const REVIEWER: i64 = 20;
+
+    fn no_limits() -> RateLimitRules {
+        RateLimitRules {
+            reviews_per_hour: 0,
+            edit_gap: 0,
+            target_per_hour: 0,
+        }
+    }
+
+    fn data() -> Data<MemoryStore> {
+        Data {
+            db: MemoryStore::default(),
+            moderation: Arc::new(ModerationPipeline::new(Vec::new())),
+            rate_limiter: RateLimiter::new(Arc::new(NoCache), no_limits()),
+            scoring: ScoreConfig {
+                mode: ScoringMode::Bayesian,
+                prior_weight: 5.0,
+                half_life: Duration::days(180),
+            },
+        }
+    }
+
+    fn form<'a>(target_id: i64, rating: &'a str, comment: &'a str, inputs: &'a [(&'a str, &'a str)]) -> ReviewForm<'a> {
+        ReviewForm {
+            target_id,
+            reviewer_id: REVIEWER,
+            review_type: ReviewType::User,
+            guild_id: None,
+            rating,
+            comment: Some(comment),
+            inputs,
+        }
+    }
+
+    async fn submit(data: &Data<MemoryStore>, rating: &str, comment: &str) -> Submission {
+        submit_review(data, form(TARGET, rating, comment, &[])).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn creates_then_updates_a_review() {
+        let data = data();
+
+        let first = submit(&data, "4", "Good trade").await;
+        assert!(matches!(first, Submission::Saved { created: true, held: None, .. }));
+
+        let second = submit(&data, "2", "Changed my mind").await;
+        assert!(matches!(second, Submission::Saved { created: false, .. }));
+        assert_eq!(second.message(), "✅ Review updated successfully!");
+
+        let stored = data.db.get_review(TARGET, REVIEWER, &ReviewType::User).await.unwrap().unwrap();
+        assert_eq!(stored.rating, 2);
+        assert_eq!(stored.comment.as_deref(), Some("Changed my mind"));
+    }
+
+    #[tokio::test]
+    async fn reports_every_invalid_field_and_saves_nothing() {
+        let data = data();
+
+        let submission = submit(&data, "9", "ok").await;
+        let message = submission.message();
+        assert!(matches!(submission, Submission::Refused(_)));
+        assert_eq!(message.lines().count(), 2, "{}", message);
+        assert!(!data.db.has_reviewed(TARGET, REVIEWER, &ReviewType::User).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn keeps_known_criteria_and_rejects_bad_values() {
+        let data = data();
+
+        let inputs = [("criterion:trustworthiness", "5"), ("criterion:removed", "3"), ("criterion:communication", "")];
+        let submission = submit_review(&data, form(TARGET, "5", "Fast and friendly", &inputs)).await.unwrap();
+        match submission {
+            Submission::Saved { review, .. } => {
+                assert_eq!(review.criteria.0, BTreeMap::from([("trustworthiness".to_string(), 5)]));
+            }
+            Submission::Refused(message) => panic!("refused: {}", message),
+        }
+
+        let inputs = [("criterion:communication", "great")];
+        let submission = submit_review(&data, form(TARGET, "5", "Fast and friendly", &inputs)).await.unwrap();
+        assert!(matches!(submission, Submission::Refused(message) if message.contains("Communication")));
+    }
+
+    #[tokio::test]
+    async fn refuses_blacklisted_reviewers() {
+        let data = data();
+        data.db.blacklist_user(REVIEWER, BlacklistScope::Reviewing, None, None, 1).await.unwrap();
+
+        let submission = submit(&data, "5", "Let me in").await;
+        assert!(matches!(submission, Submission::Refused(message) if message.contains("not allowed")));
+    }
+
+    #[tokio::test]
+    async fn holds_flagged_comments_until_approved() {
+        let mut data = data();
+        data.moderation = Arc::new(ModerationPipeline::new(vec![Box::new(WordListCheck::new(
+            Vec::new(),
+            vec!["scam".to_string()],
+        ))]));
+
+        let submission = submit(&data, "1", "Total scam").await;
+        let review = match submission {
+            Submission::Saved { review, held: Some(_), .. } => review,
+            _ => panic!("review was not held"),
+        };
+        assert_eq!(data.db.get_reviews_count(TARGET, &ReviewType::User).await.unwrap(), 0);
+
+        data.db.resolve_pending_review(review.id, 1, true).await.unwrap();
+        assert_eq!(data.db.get_reviews_count(TARGET, &ReviewType::User).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn revives_a_deleted_review_as_new() {
+        let data = data();
+        submit(&data, "3", "Was fine").await;
+        data.db.delete_review(TARGET, REVIEWER, &ReviewType::User, REVIEWER, None).await.unwrap();
+        assert!(!data.db.has_reviewed(TARGET, REVIEWER, &ReviewType::User).await.unwrap());
+
+        let submission = submit(&data, "5", "Back again").await;
+        assert!(matches!(submission, Submission::Saved { created: true, .. }));
+    }
+
+    #[tokio::test]
+    async fn limits_new_reviews_per_hour() {
+        let mut data = data();
+        let rules = RateLimitRules {
+            reviews_per_hour: 1,
+            ..no_limits()
+        };
+        data.rate_limiter = RateLimiter::new(Arc::new(MemoryCache::new(100)), rules);
+
+        let first = submit_review(&data, form(TARGET, "4", "First one", &[])).await.unwrap();
+        assert!(matches!(first, Submission::Saved { .. }));
+
+        let second = submit_review(&data, form(TARGET + 1, "4", "Second one", &[])).await.unwrap();
+        assert!(matches!(second, Submission::Refused(message) if message.starts_with("⏳")));
+    }
+}
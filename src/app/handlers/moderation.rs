@@ -0,0 +1,327 @@
+use poise::serenity_prelude::{
+    ButtonStyle,
+    ChannelId,
+    Color,
+    Context,
+    CreateComponents,
+    CreateEmbed,
+    Http,
+    InteractionResponseType,
+    MessageComponentInteraction,
+};
+use tracing::warn;
+use crate::app::{
+    utils::{store::ReviewStore, permissions::is_home_moderator},
+    models::review::{Review, ReviewType, ReviewReport, ReportResolution, TargetFlag},
+};
+
+fn moderation_channel() -> Option<ChannelId> {
+    std::env::var("MODERATION_CHANNEL")
+        .ok()
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(ChannelId)
+}
+
+pub async fn post_report<S: ReviewStore>(
+    http: &Http,
+    db: &S,
+    report: &ReviewReport,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let channel_id = match moderation_channel() {
+        Some(id) => id,
+        None => {
+            warn!("MODERATION_CHANNEL is not set, report {} was stored but not posted", report.id);
+            return Ok(());
+        }
+    };
+
+    let review = match db.get_review_by_id(report.review_id).await? {
+        Some(review) => review,
+        None => return Ok(()),
+    };
+
+    let mut embed = review_embed(&review);
+    embed
+        .title(format!("Review Report #{}", report.id))
+        .field("Reported by", format!("<@{}>", report.reporter_id), true)
+        .field("Reason", &report.reason, false)
+        .color(Color::ORANGE);
+
+    channel_id
+        .send_message(http, |m| {
+            m.set_embed(embed).components(|c| {
+                c.create_action_row(|row| {
+                    row.create_button(|b| {
+                        b.custom_id(format!("report_resolve:{}:dismissed", report.id))
+                            .label("Dismiss")
+                            .style(ButtonStyle::Secondary)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(format!("report_resolve:{}:hidden", report.id))
+                            .label("Hide review")
+                            .style(ButtonStyle::Primary)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(format!("report_resolve:{}:deleted", report.id))
+                            .label("Delete review")
+                            .style(ButtonStyle::Danger)
+                    })
+                })
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn post_pending_review(
+    http: &Http,
+    review: &Review,
+    reason: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let channel_id = match moderation_channel() {
+        Some(id) => id,
+        None => {
+            warn!("MODERATION_CHANNEL is not set, review {} is pending with no queue to approve it from", review.id);
+            return Ok(());
+        }
+    };
+
+    let mut embed = review_embed(review);
+    embed
+        .title(format!("Review #{} awaiting approval", review.id))
+        .field("Held because the comment", reason, false)
+        .color(Color::GOLD);
+
+    channel_id
+        .send_message(http, |m| {
+            m.set_embed(embed).components(|c| {
+                c.create_action_row(|row| {
+                    row.create_button(|b| {
+                        b.custom_id(format!("pending_resolve:{}:approve", review.id))
+                            .label("Approve")
+                            .style(ButtonStyle::Success)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(format!("pending_resolve:{}:reject", review.id))
+                            .label("Reject")
+                            .style(ButtonStyle::Danger)
+                    })
+                })
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn post_bombing_alert(
+    http: &Http,
+    flag: &TargetFlag,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let channel_id = match moderation_channel() {
+        Some(id) => id,
+        None => {
+            warn!("MODERATION_CHANNEL is not set, flag on {} {} was stored but not posted", flag.review_type.as_str(), flag.target_id);
+            return Ok(());
+        }
+    };
+
+    let target = match flag.review_type {
+        ReviewType::User => format!("<@{}>", flag.target_id),
+        ReviewType::Server => format!("Server `{}`", flag.target_id),
+    };
+    let average = match flag.frozen_average {
+        Some(average) => format!("Frozen at {:.1} / 5.0", average),
+        None => "Not frozen".to_string(),
+    };
+    let expires = flag
+        .expires_at
+        .map_or("Never".to_string(), |at| format!("<t:{}:R>", at.unix_timestamp()));
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Possible review bombing")
+        .description(&flag.reason)
+        .field("Target", target, true)
+        .field("Average", average, true)
+        .field("Expires", expires, true)
+        .color(Color::RED);
+
+    channel_id
+        .send_message(http, |m| {
+            m.set_embed(embed).components(|c| {
+                c.create_action_row(|row| {
+                    row.create_button(|b| {
+                        b.custom_id(format!("flag_clear:{}:{}", flag.review_type.as_str(), flag.target_id))
+                            .label("Clear flag")
+                            .style(ButtonStyle::Secondary)
+                    })
+                })
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+fn review_embed(review: &Review) -> CreateEmbed {
+    let target = match review.review_type {
+        ReviewType::User => format!("<@{}>", review.target_id),
+        ReviewType::Server => format!("Server `{}`", review.target_id),
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .description(review.comment.as_deref().unwrap_or("*No comment provided*"))
+        .field("Rating", format!("{} ({})", "⭐".repeat(review.rating as usize), review.rating), true)
+        .field("Reviewer", format!("<@{}>", review.reviewer_id), true)
+        .field("Target", target, true);
+    embed
+}
+
+async fn deny_non_moderator(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if is_home_moderator(interaction.guild_id, interaction.member.as_ref()) {
+        return Ok(false);
+    }
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content("❌ Only moderators can act on the moderation queue")
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+    Ok(true)
+}
+
+async fn close_queue_item(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    outcome: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut embed = interaction
+        .message
+        .embeds
+        .first()
+        .cloned()
+        .map(CreateEmbed::from)
+        .unwrap_or_default();
+    embed.field("Resolution", outcome, false).color(Color::DARK_GREY);
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    d.set_embed(embed).set_components(CreateComponents::default())
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_pending_resolution<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parts: Vec<&str> = custom_id.strip_prefix("pending_resolve:").unwrap().split(':').collect();
+    if parts.len() != 2 {
+        return Ok(());
+    }
+
+    let review_id = parts[0].parse::<i32>()?;
+    let approve = match parts[1] {
+        "approve" => true,
+        "reject" => false,
+        _ => return Ok(()),
+    };
+
+    if deny_non_moderator(ctx, interaction).await? {
+        return Ok(());
+    }
+
+    let moderator_id = interaction.user.id.0 as i64;
+    let outcome = match db.resolve_pending_review(review_id, moderator_id, approve).await? {
+        Some(_) if approve => format!("Approved by <@{}>", moderator_id),
+        Some(_) => format!("Rejected by <@{}>", moderator_id),
+        None => "No longer pending".to_string(),
+    };
+
+    close_queue_item(ctx, interaction, outcome).await
+}
+
+pub async fn handle_resolution<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parts: Vec<&str> = custom_id.strip_prefix("report_resolve:").unwrap().split(':').collect();
+    if parts.len() != 2 {
+        return Ok(());
+    }
+
+    let report_id = parts[0].parse::<i32>()?;
+    let resolution = match ReportResolution::parse(parts[1]) {
+        Some(resolution) => resolution,
+        None => return Ok(()),
+    };
+
+    if deny_non_moderator(ctx, interaction).await? {
+        return Ok(());
+    }
+
+    let moderator_id = interaction.user.id.0 as i64;
+    let outcome = match db.resolve_report(report_id, moderator_id, resolution.clone()).await? {
+        Some(_) => {
+            let action = match resolution {
+                ReportResolution::Dismissed => "Dismissed",
+                ReportResolution::Hidden => "Review hidden",
+                ReportResolution::Deleted => "Review deleted",
+            };
+            format!("{} by <@{}>", action, moderator_id)
+        }
+        None => "Already resolved".to_string(),
+    };
+
+    close_queue_item(ctx, interaction, outcome).await
+}
+
+pub async fn handle_flag_clear<S: ReviewStore>(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    db: &S,
+    custom_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let parts: Vec<&str> = custom_id.strip_prefix("flag_clear:").unwrap().split(':').collect();
+    if parts.len() != 2 {
+        return Ok(());
+    }
+
+    let review_type = match ReviewType::parse(parts[0]) {
+        Some(review_type) => review_type,
+        None => return Ok(()),
+    };
+    let target_id = parts[1].parse::<i64>()?;
+
+    if deny_non_moderator(ctx, interaction).await? {
+        return Ok(());
+    }
+
+    let moderator_id = interaction.user.id.0 as i64;
+    let outcome = if db.clear_target_flag(target_id, &review_type, moderator_id).await? {
+        format!("Cleared by <@{}>", moderator_id)
+    } else {
+        "Already cleared or expired".to_string()
+    };
+
+    close_queue_item(ctx, interaction, outcome).await
+}
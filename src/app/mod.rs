@@ -1,5 +1,5 @@
-pub mod commands;
-pub mod handlers;
-pub mod models;
-pub mod utils;
+pub mod commands;
+pub mod handlers;
+pub mod models;
+pub mod utils;
 pub mod events; 
\ No newline at end of file
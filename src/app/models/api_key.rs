@@ -0,0 +1,47 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+use crate::app::utils::datetime::datetime_format;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ApiScope {
+    #[name = "reviews:read"]
+    ReviewsRead,
+    #[name = "reviews:write"]
+    ReviewsWrite,
+    #[name = "admin"]
+    Admin,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::ReviewsRead => "reviews:read",
+            ApiScope::ReviewsWrite => "reviews:write",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: i64,
+    #[serde(with = "datetime_format")]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_format")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_format")]
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+impl ApiKey {
+    // Admin keys can do everything the other scopes allow
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| s == scope.as_str() || s == ApiScope::Admin.as_str())
+    }
+}
@@ -1 +1,2 @@
-pub mod review;
\ No newline at end of file
+pub mod review;
+pub mod api_key;
\ No newline at end of file
@@ -1,67 +1,365 @@
-use serde::{Serialize, Deserialize};
-use time::OffsetDateTime;
-use crate::app::utils::datetime::datetime_format;
-
-#[derive(Debug, Clone, sqlx::Type, Serialize, Deserialize)]
-#[sqlx(type_name = "review_type", rename_all = "snake_case")]
-pub enum ReviewType {
-    User,
-    Server,
-}
-
-impl ReviewType {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            ReviewType::User => "user",
-            ReviewType::Server => "server",
-        }
-    }
-}
-
-#[derive(Debug, sqlx::FromRow, Serialize)]
-pub struct Review {
-    pub id: i32,
-    pub target_id: i64,
-    pub reviewer_id: i64,
-    pub rating: i32,
-    pub comment: Option<String>,
-    pub review_type: ReviewType,
-    #[serde(with = "datetime_format")]
-    pub created_at: Option<OffsetDateTime>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum RatingCategory {
-    Unrated,
-    Poor,
-    Fair,
-    Good,
-    VeryGood,
-    Excellent,
-}
-
-impl RatingCategory {
-    pub fn from_average(avg: f64) -> Self {
-        match avg {
-            x if x == 0.0 => Self::Unrated,
-            x if x <= 1.0 => Self::Poor,
-            x if x <= 2.0 => Self::Fair,
-            x if x <= 3.0 => Self::Good,
-            x if x <= 4.0 => Self::VeryGood,
-            _ => Self::Excellent,
-        }
-    }
-}
-
-impl std::fmt::Display for RatingCategory {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Unrated => write!(f, "Unrated"),
-            Self::Poor => write!(f, "Poor"),
-            Self::Fair => write!(f, "Fair"),
-            Self::Good => write!(f, "Good"),
-            Self::VeryGood => write!(f, "Very Good"),
-            Self::Excellent => write!(f, "Excellent"),
-        }
-    }
+use serde::{Serialize, Deserialize};
+use sqlx::types::Json;
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+use crate::app::utils::datetime::datetime_format;
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize, Deserialize, poise::ChoiceParameter)]
+#[sqlx(type_name = "review_type", rename_all = "snake_case")]
+pub enum ReviewType {
+    User,
+    Server,
+}
+
+impl ReviewType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewType::User => "user",
+            ReviewType::Server => "server",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "user" => Some(ReviewType::User),
+            "server" => Some(ReviewType::Server),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "review_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    Published,
+    Hidden,
+    Pending,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct Review {
+    pub id: i32,
+    pub target_id: i64,
+    pub reviewer_id: i64,
+    pub rating: i32,
+    pub comment: Option<String>,
+    pub review_type: ReviewType,
+    #[serde(with = "datetime_format")]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_format")]
+    pub updated_at: Option<OffsetDateTime>,
+    pub criteria: Json<BTreeMap<String, i32>>,
+    pub helpful_votes: i32,
+    pub unhelpful_votes: i32,
+    pub reply: Option<String>,
+    pub reply_by: Option<i64>,
+    #[serde(with = "datetime_format")]
+    pub replied_at: Option<OffsetDateTime>,
+}
+
+// Order of paginated reviews
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReviewSort {
+    #[default]
+    Newest,
+    Oldest,
+    Highest,
+    Lowest,
+    Helpful,
+}
+
+impl ReviewSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewSort::Newest => "newest",
+            ReviewSort::Oldest => "oldest",
+            ReviewSort::Highest => "highest",
+            ReviewSort::Lowest => "lowest",
+            ReviewSort::Helpful => "helpful",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "newest" => Some(ReviewSort::Newest),
+            "oldest" => Some(ReviewSort::Oldest),
+            "highest" => Some(ReviewSort::Highest),
+            "lowest" => Some(ReviewSort::Lowest),
+            "helpful" => Some(ReviewSort::Helpful),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReviewSort::Newest => "Newest first",
+            ReviewSort::Oldest => "Oldest first",
+            ReviewSort::Highest => "Highest rated first",
+            ReviewSort::Lowest => "Lowest rated first",
+            ReviewSort::Helpful => "Most helpful first",
+        }
+    }
+
+    // The order the paginator's sort button switches to
+    pub fn next(&self) -> Self {
+        match self {
+            ReviewSort::Newest => ReviewSort::Helpful,
+            ReviewSort::Helpful => ReviewSort::Highest,
+            ReviewSort::Highest => ReviewSort::Lowest,
+            ReviewSort::Lowest => ReviewSort::Oldest,
+            ReviewSort::Oldest => ReviewSort::Newest,
+        }
+    }
+}
+
+// Order and filters for listing a target's reviews, shared by the API and the paginator
+#[derive(Debug, Clone, Default)]
+pub struct ReviewQuery {
+    pub sort: ReviewSort,
+    pub min_rating: Option<i32>,
+    pub max_rating: Option<i32>,
+    pub has_comment: Option<bool>,
+    pub since: Option<OffsetDateTime>,
+    pub until: Option<OffsetDateTime>,
+}
+
+impl ReviewQuery {
+    pub fn sorted(sort: ReviewSort) -> Self {
+        Self { sort, ..Self::default() }
+    }
+}
+
+// Vote counts of a review after a vote, and what the voter's vote is now
+#[derive(Debug, Clone, Copy)]
+pub struct VoteTally {
+    pub helpful_votes: i32,
+    pub unhelpful_votes: i32,
+    pub vote: Option<bool>,
+}
+
+// An aspect reviews rate separately, such as a server's moderation
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow, Serialize)]
+pub struct RatingCriterion {
+    pub key: String,
+    pub label: String,
+}
+
+impl RatingCriterion {
+    pub fn from_label(label: &str) -> Self {
+        let key = label
+            .trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+
+        Self { key, label: label.trim().to_string() }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct ReviewRevision {
+    pub id: i32,
+    pub review_id: i32,
+    pub rating: i32,
+    pub comment: Option<String>,
+    #[serde(with = "datetime_format")]
+    pub written_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_format")]
+    pub replaced_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "report_resolution", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ReportResolution {
+    Dismissed,
+    Hidden,
+    Deleted,
+}
+
+impl ReportResolution {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "dismissed" => Some(ReportResolution::Dismissed),
+            "hidden" => Some(ReportResolution::Hidden),
+            "deleted" => Some(ReportResolution::Deleted),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct ReviewReport {
+    pub id: i32,
+    pub review_id: i32,
+    pub reporter_id: i64,
+    pub reason: String,
+    #[serde(with = "datetime_format")]
+    pub created_at: Option<OffsetDateTime>,
+    pub resolution: Option<ReportResolution>,
+    pub resolved_by: Option<i64>,
+    #[serde(with = "datetime_format")]
+    pub resolved_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize, Deserialize, poise::ChoiceParameter)]
+#[sqlx(type_name = "blacklist_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum BlacklistScope {
+    #[name = "Cannot write reviews"]
+    Reviewing,
+    #[name = "Cannot be reviewed"]
+    Reviewed,
+    #[name = "Hidden everywhere"]
+    Everywhere,
+}
+
+impl BlacklistScope {
+    pub fn blocks_reviewing(&self) -> bool {
+        matches!(self, BlacklistScope::Reviewing | BlacklistScope::Everywhere)
+    }
+
+    pub fn blocks_being_reviewed(&self) -> bool {
+        matches!(self, BlacklistScope::Reviewed | BlacklistScope::Everywhere)
+    }
+}
+
+// Which reviews the displayed score is computed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::Type, Serialize, Deserialize, poise::ChoiceParameter)]
+#[sqlx(type_name = "score_view", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreView {
+    #[default]
+    #[name = "All time"]
+    AllTime,
+    #[name = "Last 90 days"]
+    Recent,
+    #[name = "Decayed by age"]
+    Decayed,
+}
+
+impl ScoreView {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "all_time" => Some(ScoreView::AllTime),
+            "recent" => Some(ScoreView::Recent),
+            "decayed" => Some(ScoreView::Decayed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct BlacklistEntry {
+    pub id: i32,
+    pub user_id: i64,
+    pub scope: BlacklistScope,
+    pub reason: Option<String>,
+    pub created_by: i64,
+    #[serde(with = "datetime_format")]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_format")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct TargetFlag {
+    pub id: i32,
+    pub target_id: i64,
+    pub review_type: ReviewType,
+    pub reason: String,
+    pub frozen_average: Option<f64>,
+    #[serde(with = "datetime_format")]
+    pub flagged_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_format")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+// Review activity of one target, split at the start of the detection window
+#[derive(Debug, Clone)]
+pub struct TargetActivity {
+    pub target_id: i64,
+    pub review_type: ReviewType,
+    pub recent_reviews: i64,
+    pub recent_average: f64,
+    pub baseline_average: Option<f64>,
+    pub recent_reviewers: Vec<i64>,
+}
+
+// Rating totals of one target, ranked by score on leaderboards
+#[derive(Debug, Clone)]
+pub struct RatedTarget {
+    pub target_id: i64,
+    pub review_count: i64,
+    pub average: f64,
+}
+
+// Average of the reviews a score view counts, with each review weighted by age in the decayed view
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeightedRating {
+    pub average: Option<f64>,
+    pub weight: f64,
+}
+
+// How many reviews gave each number of stars
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RatingDistribution {
+    #[serde(rename = "1")]
+    pub one: i64,
+    #[serde(rename = "2")]
+    pub two: i64,
+    #[serde(rename = "3")]
+    pub three: i64,
+    #[serde(rename = "4")]
+    pub four: i64,
+    #[serde(rename = "5")]
+    pub five: i64,
+}
+
+impl RatingDistribution {
+    pub fn from_counts([one, two, three, four, five]: [i64; 5]) -> Self {
+        Self { one, two, three, four, five }
+    }
+
+    pub fn counts(&self) -> [i64; 5] {
+        [self.one, self.two, self.three, self.four, self.five]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RatingCategory {
+    Unrated,
+    Poor,
+    Fair,
+    Good,
+    VeryGood,
+    Excellent,
+}
+
+impl RatingCategory {
+    pub fn from_average(avg: f64) -> Self {
+        match avg {
+            0.0 => Self::Unrated,
+            x if x <= 1.0 => Self::Poor,
+            x if x <= 2.0 => Self::Fair,
+            x if x <= 3.0 => Self::Good,
+            x if x <= 4.0 => Self::VeryGood,
+            _ => Self::Excellent,
+        }
+    }
+}
+
+impl std::fmt::Display for RatingCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unrated => write!(f, "Unrated"),
+            Self::Poor => write!(f, "Poor"),
+            Self::Fair => write!(f, "Fair"),
+            Self::Good => write!(f, "Good"),
+            Self::VeryGood => write!(f, "Very Good"),
+            Self::Excellent => write!(f, "Excellent"),
+        }
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,29 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+pub const KEY_PREFIX: &str = "rvw_";
+// Characters of a key shown in listings so owners can tell keys apart
+const VISIBLE_LENGTH: usize = 12;
+
+pub struct IssuedKey {
+    pub key: String,
+    pub prefix: String,
+    pub hash: String,
+}
+
+pub fn generate() -> IssuedKey {
+    let mut secret = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    let key = format!("{}{}", KEY_PREFIX, hex::encode(secret));
+    IssuedKey {
+        prefix: key[..VISIBLE_LENGTH].to_string(),
+        hash: hash(&key),
+        key,
+    }
+}
+
+// Keys are random and long, so a plain SHA-256 is enough to keep them out of the database
+pub fn hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
@@ -0,0 +1,47 @@
+use tracing::{error, info};
+use crate::app::{models::review::{BlacklistScope, ReviewType}, utils::store::ReviewStore};
+
+// Seconds between sweeps for expired entries
+const EXPIRY_SWEEP_INTERVAL: u64 = 60;
+
+// Returns why the review cannot be written, if a blacklist entry forbids it
+pub async fn review_denial<S: ReviewStore>(
+    db: &S,
+    reviewer_id: i64,
+    target_id: i64,
+    review_type: &ReviewType,
+) -> Result<Option<&'static str>, sqlx::Error> {
+    let reviewer = db.get_active_blacklist(reviewer_id).await?;
+    if reviewer.iter().any(|e| e.scope.blocks_reviewing()) {
+        return Ok(Some("You are not allowed to write reviews"));
+    }
+
+    if *review_type == ReviewType::User {
+        let target = db.get_active_blacklist(target_id).await?;
+        if target.iter().any(|e| e.scope.blocks_being_reviewed()) {
+            return Ok(Some("This user cannot be reviewed"));
+        }
+    }
+
+    Ok(None)
+}
+
+pub async fn is_hidden_everywhere<S: ReviewStore>(db: &S, user_id: i64) -> Result<bool, sqlx::Error> {
+    let entries = db.get_active_blacklist(user_id).await?;
+    Ok(entries.iter().any(|e| e.scope == BlacklistScope::Everywhere))
+}
+
+// Expired entries stop hiding reviews straight away, but the aggregates only catch up once the entry is swept
+pub fn spawn_expiry_sweep<S: ReviewStore>(db: S) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(EXPIRY_SWEEP_INTERVAL));
+        loop {
+            interval.tick().await;
+            match db.purge_expired_blacklist().await {
+                Ok(0) => {}
+                Ok(purged) => info!("Removed {} expired blacklist entries", purged),
+                Err(e) => error!("Blacklist expiry sweep failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,240 @@
+use std::{env, sync::Arc};
+use poise::serenity_prelude::Http;
+use time::{Duration, OffsetDateTime};
+use tracing::{error, info, warn};
+use crate::app::{
+    handlers::moderation,
+    models::review::TargetActivity,
+    utils::store::ReviewStore,
+};
+
+// Milliseconds between the Unix epoch and the Discord epoch (2015-01-01)
+const DISCORD_EPOCH: i64 = 1_420_070_400_000;
+
+#[derive(Debug, Clone)]
+pub struct BombingRules {
+    pub interval: u64,
+    pub window: Duration,
+    pub min_reviews: i64,
+    pub rating_skew: f64,
+    pub new_account_age: Duration,
+    pub new_account_share: f64,
+    pub flag_duration: Duration,
+    pub freeze_average: bool,
+}
+
+impl BombingRules {
+    pub fn from_env() -> Self {
+        let number = |name: &str, default: f64| -> f64 {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            interval: number("BOMBING_CHECK_INTERVAL", 300.0) as u64,
+            window: Duration::minutes(number("BOMBING_WINDOW_MINUTES", 60.0) as i64),
+            min_reviews: number("BOMBING_MIN_REVIEWS", 5.0) as i64,
+            rating_skew: number("BOMBING_RATING_SKEW", 2.0),
+            new_account_age: Duration::days(number("BOMBING_NEW_ACCOUNT_DAYS", 30.0) as i64),
+            new_account_share: number("BOMBING_NEW_ACCOUNT_SHARE", 0.5),
+            flag_duration: Duration::hours(number("BOMBING_FLAG_HOURS", 24.0) as i64),
+            freeze_average: env::var("BOMBING_FREEZE_AVERAGE").map_or(true, |v| v != "false"),
+        }
+    }
+
+    /// A burst alone is not enough, it also has to pull the rating away from the target's
+    /// history or come mostly from fresh accounts.
+    pub fn assess(&self, activity: &TargetActivity, now: OffsetDateTime) -> Option<String> {
+        if activity.recent_reviews < self.min_reviews {
+            return None;
+        }
+
+        let mut signals = Vec::new();
+
+        if let Some(baseline) = activity.baseline_average {
+            let skew = (baseline - activity.recent_average).abs();
+            if skew >= self.rating_skew {
+                signals.push(format!(
+                    "recent reviews average {:.1} against {:.1} before",
+                    activity.recent_average, baseline
+                ));
+            }
+        }
+
+        let fresh = activity
+            .recent_reviewers
+            .iter()
+            .filter(|id| now - account_created_at(**id) < self.new_account_age)
+            .count();
+        let share = fresh as f64 / activity.recent_reviewers.len().max(1) as f64;
+        if share >= self.new_account_share {
+            signals.push(format!(
+                "{} of {} reviewers have accounts younger than {} days",
+                fresh,
+                activity.recent_reviewers.len(),
+                self.new_account_age.whole_days()
+            ));
+        }
+
+        if signals.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "{} reviews in the last {} minutes; {}",
+            activity.recent_reviews,
+            self.window.whole_minutes(),
+            signals.join("; ")
+        ))
+    }
+}
+
+pub fn account_created_at(user_id: i64) -> OffsetDateTime {
+    let millis = (user_id >> 22) + DISCORD_EPOCH;
+    OffsetDateTime::from_unix_timestamp(millis / 1000).unwrap_or(OffsetDateTime::UNIX_EPOCH)
+}
+
+pub fn spawn_detector<S: ReviewStore>(db: S, http: Arc<Http>, rules: BombingRules) {
+    if rules.interval == 0 {
+        info!("Review bombing detection is disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(rules.interval));
+        loop {
+            interval.tick().await;
+            if let Err(e) = scan(&db, &http, &rules).await {
+                error!("Review bombing scan failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn scan<S: ReviewStore>(
+    db: &S,
+    http: &Http,
+    rules: &BombingRules,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let now = OffsetDateTime::now_utc();
+    let since = now - rules.window;
+
+    for activity in db.get_review_activity(since, rules.min_reviews).await? {
+        let reason = match rules.assess(&activity, now) {
+            Some(reason) => reason,
+            None => continue,
+        };
+
+        let frozen_average = if rules.freeze_average { activity.baseline_average } else { None };
+        let flag = db.flag_target(
+            activity.target_id,
+            &activity.review_type,
+            reason,
+            frozen_average,
+            since,
+            now + rules.flag_duration,
+        ).await?;
+
+        if let Some(flag) = flag {
+            warn!("Flagged {} {} for review bombing: {}", flag.review_type.as_str(), flag.target_id, flag.reason);
+            moderation::post_bombing_alert(http, &flag).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::models::review::ReviewType;
+
+    fn rules() -> BombingRules {
+        BombingRules {
+            interval: 300,
+            window: Duration::minutes(60),
+            min_reviews: 4,
+            rating_skew: 2.0,
+            new_account_age: Duration::days(30),
+            new_account_share: 0.5,
+            flag_duration: Duration::hours(24),
+            freeze_average: true,
+        }
+    }
+
+    fn now() -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()
+    }
+
+    // The smallest snowflake Discord could have handed out at `created_at`
+    fn snowflake(created_at: OffsetDateTime) -> i64 {
+        let millis = (created_at.unix_timestamp_nanos() / 1_000_000) as i64;
+        (millis - DISCORD_EPOCH) << 22
+    }
+
+    // `fresh` of the reviewers signed up a day ago, the rest years ago
+    fn activity(recent_average: f64, baseline_average: Option<f64>, reviewers: usize, fresh: usize) -> TargetActivity {
+        let recent_reviewers = (0..reviewers)
+            .map(|i| {
+                let age = if i < fresh { Duration::days(1) } else { Duration::days(1000) };
+                snowflake(now() - age) + i as i64
+            })
+            .collect();
+        TargetActivity {
+            target_id: 10,
+            review_type: ReviewType::User,
+            recent_reviews: reviewers as i64,
+            recent_average,
+            baseline_average,
+            recent_reviewers,
+        }
+    }
+
+    #[test]
+    fn decodes_account_age_from_snowflakes() {
+        // The example ID from Discord's API reference
+        assert_eq!(account_created_at(175928847299117063).unix_timestamp(), 1_462_015_105);
+        assert_eq!(account_created_at(0), OffsetDateTime::from_unix_timestamp(DISCORD_EPOCH / 1000).unwrap());
+        assert_eq!(account_created_at(snowflake(now())), now());
+    }
+
+    #[test]
+    fn small_bursts_are_never_flagged() {
+        assert_eq!(rules().assess(&activity(1.0, Some(5.0), 3, 3), now()), None);
+    }
+
+    #[test]
+    fn a_burst_needs_another_signal() {
+        assert_eq!(rules().assess(&activity(4.0, Some(4.5), 10, 0), now()), None);
+        assert_eq!(rules().assess(&activity(1.0, None, 10, 0), now()), None);
+    }
+
+    #[test]
+    fn flags_a_skewed_rating() {
+        let reason = rules().assess(&activity(1.0, Some(4.5), 10, 0), now()).unwrap();
+        assert!(reason.contains("average 1.0 against 4.5"), "{}", reason);
+        assert!(!reason.contains("younger"), "{}", reason);
+
+        assert!(rules().assess(&activity(2.0, Some(4.0), 4, 0), now()).is_some());
+        assert_eq!(rules().assess(&activity(2.1, Some(4.0), 4, 0), now()), None);
+    }
+
+    #[test]
+    fn flags_mostly_fresh_accounts() {
+        let reason = rules().assess(&activity(4.0, Some(4.0), 5, 3), now()).unwrap();
+        assert!(reason.contains("3 of 5 reviewers"), "{}", reason);
+        assert!(!reason.contains("against"), "{}", reason);
+
+        assert!(rules().assess(&activity(4.0, None, 4, 2), now()).is_some());
+        assert_eq!(rules().assess(&activity(4.0, None, 5, 2), now()), None);
+    }
+
+    #[test]
+    fn reports_every_signal() {
+        let reason = rules().assess(&activity(1.0, Some(4.5), 4, 4), now()).unwrap();
+        assert!(reason.starts_with("4 reviews in the last 60 minutes"), "{}", reason);
+        assert!(reason.contains("against") && reason.contains("4 of 4 reviewers"), "{}", reason);
+    }
+}
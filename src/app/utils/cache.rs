@@ -1,88 +1,98 @@
-use redis::{Client, Commands, RedisResult};
-use tracing::{error, info};
-
-const CACHE_DURATION: usize = 600;
-
-#[derive(Clone)]
-pub struct Cache {
-    client: Client,
-}
-
-impl Cache {
-    pub fn new(redis_url: &str) -> Result<Self, redis::RedisError> {
-        let client = Client::open(redis_url)?;
-        Ok(Self { client })
-    }
-
-    pub async fn cache_rating(&self, target_id: i64, average: f64, prefix: &str) -> RedisResult<()> {
-        let mut conn = self.client.get_connection()?;
-        let key = format!("{}:{}:rating", prefix, target_id);
-        let _: () = conn.set_ex(key, average.to_string(), CACHE_DURATION)?;
-        info!("Cached {} rating for ID {}: {}", prefix, target_id, average);
-        Ok(())
-    }
-
-    pub async fn get_rating(&self, target_id: i64, prefix: &str) -> RedisResult<Option<f64>> {
-        let mut conn = self.client.get_connection()?;
-        let key = format!("{}:{}:rating", prefix, target_id);
-        let value: Option<String> = conn.get(key)?;
-        
-        match value {
-            Some(v) => match v.parse() {
-                Ok(rating) => {
-                    info!("Cache hit for {} rating ID {}", prefix, target_id);
-                    Ok(Some(rating))
-                },
-                Err(e) => {
-                    error!("Failed to parse cached rating: {}", e);
-                    Ok(None)
-                }
-            },
-            None => {
-                info!("Cache miss for {} rating ID {}", prefix, target_id);
-                Ok(None)
-            }
-        }
-    }
-
-    pub async fn cache_count(&self, target_id: i64, count: i64, prefix: &str) -> RedisResult<()> {
-        let mut conn = self.client.get_connection()?;
-        let key = format!("{}:{}:count", prefix, target_id);
-        let _: () = conn.set_ex(key, count.to_string(), CACHE_DURATION)?;
-        info!("Cached {} count for ID {}: {}", prefix, target_id, count);
-        Ok(())
-    }
-
-    pub async fn get_count(&self, target_id: i64, prefix: &str) -> RedisResult<Option<i64>> {
-        let mut conn = self.client.get_connection()?;
-        let key = format!("{}:{}:count", prefix, target_id);
-        let value: Option<String> = conn.get(key)?;
-        
-        match value {
-            Some(v) => match v.parse() {
-                Ok(count) => {
-                    info!("Cache hit for {} count ID {}", prefix, target_id);
-                    Ok(Some(count))
-                },
-                Err(e) => {
-                    error!("Failed to parse cached count: {}", e);
-                    Ok(None)
-                }
-            },
-            None => {
-                info!("Cache miss for {} count ID {}", prefix, target_id);
-                Ok(None)
-            }
-        }
-    }
-
-    pub async fn invalidate(&self, target_id: i64, prefix: &str) -> RedisResult<()> {
-        let mut conn = self.client.get_connection()?;
-        let rating_key = format!("{}:{}:rating", prefix, target_id);
-        let count_key = format!("{}:{}:count", prefix, target_id);
-        
-        let _: () = conn.del(&[rating_key, count_key])?;
-        info!("Invalidated cache for {} ID {}", prefix, target_id);
-        Ok(())
-    }
-} 
\ No newline at end of file
+use async_trait::async_trait;
+use redis::RedisResult;
+use std::{env, sync::Arc, time::Duration};
+use tracing::info;
+use crate::app::utils::{memory_cache::MemoryCache, redis_cache::RedisCache};
+
+pub const CACHE_DURATION: usize = 600;
+
+pub type Cache = Arc<dyn CacheBackend>;
+
+// A cached value together with the time it has left before it expires
+#[derive(Debug, Clone, Copy)]
+pub struct Cached<T> {
+    pub value: T,
+    pub ttl: Duration,
+}
+
+// Ratings and counts per target, plus the counters the rate limiter keeps
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn cache_rating(&self, target_id: i64, average: f64, prefix: &str) -> RedisResult<()>;
+    async fn get_rating(&self, target_id: i64, prefix: &str) -> RedisResult<Option<Cached<f64>>>;
+    async fn cache_count(&self, target_id: i64, count: i64, prefix: &str) -> RedisResult<()>;
+    async fn get_count(&self, target_id: i64, prefix: &str) -> RedisResult<Option<Cached<i64>>>;
+    async fn invalidate_many(&self, targets: &[(i64, &str)]) -> RedisResult<()>;
+
+    async fn invalidate(&self, target_id: i64, prefix: &str) -> RedisResult<()> {
+        self.invalidate_many(&[(target_id, prefix)]).await?;
+        info!("Invalidated cache for {} ID {}", prefix, target_id);
+        Ok(())
+    }
+
+    /// Counts a hit in a fixed window that starts with the first hit, returns the new count
+    /// and the seconds left in the window.
+    async fn hit_window(&self, key: &str, window: usize) -> RedisResult<(i64, i64)>;
+    /// Takes back a hit counted by `hit_window`, leaving the window's expiry alone.
+    async fn release_window(&self, key: &str) -> RedisResult<()>;
+    async fn set_cooldown(&self, key: &str, seconds: usize) -> RedisResult<()>;
+    /// Starts a cooldown unless one is running, returns the seconds left on a running one.
+    async fn claim_cooldown(&self, key: &str, seconds: usize) -> RedisResult<Option<i64>>;
+}
+
+pub async fn from_env() -> Result<Cache, Box<dyn std::error::Error + Send + Sync>> {
+    let backend = env::var("CACHE_BACKEND").unwrap_or_else(|_| "redis".to_string());
+    info!("Using {} cache backend", backend);
+
+    match backend.as_str() {
+        "redis" => {
+            let redis_url = env::var("REDIS_URL").map_err(|_| "Missing REDIS_URL")?;
+            Ok(Arc::new(RedisCache::new(&redis_url).await?))
+        }
+        "memory" => Ok(Arc::new(MemoryCache::from_env())),
+        "none" => Ok(Arc::new(NoCache)),
+        other => Err(format!("Unknown CACHE_BACKEND '{}', expected memory, redis or none", other).into()),
+    }
+}
+
+// Every read misses, so all values come straight from Postgres and rate limits never trip
+pub struct NoCache;
+
+#[async_trait]
+impl CacheBackend for NoCache {
+    async fn cache_rating(&self, _target_id: i64, _average: f64, _prefix: &str) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn get_rating(&self, _target_id: i64, _prefix: &str) -> RedisResult<Option<Cached<f64>>> {
+        Ok(None)
+    }
+
+    async fn cache_count(&self, _target_id: i64, _count: i64, _prefix: &str) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn get_count(&self, _target_id: i64, _prefix: &str) -> RedisResult<Option<Cached<i64>>> {
+        Ok(None)
+    }
+
+    async fn invalidate_many(&self, _targets: &[(i64, &str)]) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn hit_window(&self, _key: &str, _window: usize) -> RedisResult<(i64, i64)> {
+        Ok((0, 0))
+    }
+
+    async fn release_window(&self, _key: &str) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn set_cooldown(&self, _key: &str, _seconds: usize) -> RedisResult<()> {
+        Ok(())
+    }
+
+    async fn claim_cooldown(&self, _key: &str, _seconds: usize) -> RedisResult<Option<i64>> {
+        Ok(None)
+    }
+}
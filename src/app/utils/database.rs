@@ -1,197 +1,1627 @@
-use sqlx::{Pool, Postgres, postgres::PgPoolOptions};
-use crate::app::{
-    models::review::{Review, ReviewType},
-    utils::cache::Cache,
-};
-use std::time::Duration;
-
-#[derive(Clone)]
-pub struct Database {
-    pool: Pool<Postgres>,
-    cache: Cache,
-}
-
-impl Database {
-    pub async fn new(database_url: &str, cache: Cache) -> Result<Self, sqlx::Error> {
-        let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(Duration::from_secs(3))
-            .connect(database_url)
-            .await?;
-
-        Ok(Self { pool, cache })
-    }
-
-    pub async fn add_review(
-        &self,
-        target_id: i64,
-        reviewer_id: i64,
-        rating: i32,
-        comment: Option<String>,
-        review_type: &ReviewType,
-    ) -> Result<Review, sqlx::Error> {
-        let review = sqlx::query_as!(
-            Review,
-            r#"
-            INSERT INTO reviews (target_id, reviewer_id, rating, comment, review_type)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, target_id, reviewer_id, rating, comment, 
-                review_type as "review_type: ReviewType",
-                created_at
-            "#,
-            target_id,
-            reviewer_id,
-            rating,
-            comment,
-            review_type as &ReviewType
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        let _ = self.cache.invalidate(target_id, review_type.as_str()).await;
-
-        Ok(review)
-    }
-
-    pub async fn get_average_rating(
-        &self,
-        target_id: i64,
-        review_type: &ReviewType,
-    ) -> Result<Option<f64>, sqlx::Error> {
-        let prefix = review_type.as_str();
-
-        if let Ok(Some(rating)) = self.cache.get_rating(target_id, prefix).await {
-            return Ok(Some(rating));
-        }
-
-        let result = sqlx::query!(
-            r#"
-            SELECT AVG(rating::float) as average 
-            FROM reviews 
-            WHERE target_id = $1 AND review_type = $2
-            "#,
-            target_id,
-            review_type as &ReviewType
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        if let Some(avg) = result.average {
-            let _ = self.cache.cache_rating(target_id, avg, prefix).await;
-        }
-
-        Ok(result.average)
-    }
-
-    pub async fn get_reviews_count(
-        &self,
-        target_id: i64,
-        review_type: &ReviewType,
-    ) -> Result<i64, sqlx::Error> {
-        let prefix = review_type.as_str();
-
-        if let Ok(Some(count)) = self.cache.get_count(target_id, prefix).await {
-            return Ok(count);
-        }
-
-        let result = sqlx::query!(
-            r#"
-            SELECT COUNT(*) as count 
-            FROM reviews 
-            WHERE target_id = $1 AND review_type = $2
-            "#,
-            target_id,
-            review_type as &ReviewType
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        let count = result.count.unwrap_or(0);
-        let _ = self.cache.cache_count(target_id, count, prefix).await;
-
-        Ok(count)
-    }
-
-    pub async fn has_reviewed(
-        &self,
-        target_id: i64,
-        reviewer_id: i64,
-        review_type: &ReviewType,
-    ) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query!(
-            r#"
-            SELECT EXISTS(
-                SELECT 1 
-                FROM reviews 
-                WHERE target_id = $1 AND reviewer_id = $2 AND review_type = $3
-            ) as exists
-            "#,
-            target_id,
-            reviewer_id,
-            review_type as &ReviewType
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        Ok(result.exists.unwrap_or(false))
-    }
-
-    pub async fn update_review(
-        &self,
-        target_id: i64,
-        reviewer_id: i64,
-        rating: i32,
-        comment: Option<String>,
-        review_type: &ReviewType,
-    ) -> Result<Review, sqlx::Error> {
-        let review = sqlx::query_as!(
-            Review,
-            r#"
-            UPDATE reviews 
-            SET rating = $3, comment = $4, created_at = CURRENT_TIMESTAMP
-            WHERE target_id = $1 AND reviewer_id = $2 AND review_type = $5
-            RETURNING id, target_id, reviewer_id, rating, comment, 
-                review_type as "review_type: ReviewType",
-                created_at
-            "#,
-            target_id,
-            reviewer_id,
-            rating,
-            comment,
-            review_type as &ReviewType
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        let _ = self.cache.invalidate(target_id, review_type.as_str()).await;
-
-        Ok(review)
-    }
-
-    pub async fn get_paginated_reviews(
-        &self,
-        target_id: i64,
-        page: i64,
-        per_page: i64,
-        review_type: &ReviewType,
-    ) -> Result<Vec<Review>, sqlx::Error> {
-        sqlx::query_as!(
-            Review,
-            r#"
-            SELECT id, target_id, reviewer_id, rating, comment, 
-                review_type as "review_type: ReviewType",
-                created_at
-            FROM reviews 
-            WHERE target_id = $1 AND review_type = $2
-            ORDER BY created_at DESC
-            LIMIT $3 OFFSET $4
-            "#,
-            target_id,
-            review_type as &ReviewType,
-            per_page,
-            page * per_page
-        )
-        .fetch_all(&self.pool)
-        .await
-    }
+use sqlx::{Pool, Postgres, PgConnection, postgres::PgPoolOptions, migrate::Migrator};
+use crate::app::{
+    models::{
+        api_key::ApiKey,
+        review::{
+            Review, ReviewRevision, ReviewStatus, ReviewType, ReviewReport, ReportResolution,
+            BlacklistEntry, BlacklistScope, TargetFlag, TargetActivity, RatingDistribution,
+            RatedTarget, ScoreView, WeightedRating, RatingCriterion, ReviewQuery, VoteTally,
+        },
+    },
+    utils::{cache::Cache, store::ReviewStore, single_flight::SingleFlight},
+};
+use std::{
+    collections::BTreeMap,
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tracing::info;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+const DEFAULT_RECOMPUTE_MS: u64 = 20;
+// Higher values refresh cached summaries earlier
+const EARLY_REFRESH_BETA: f64 = 1.0;
+
+pub async fn connect(database_url: &str) -> Result<Pool<Postgres>, sqlx::Error> {
+    PgPoolOptions::new()
+        .max_connections(5)
+        .acquire_timeout(Duration::from_secs(3))
+        .connect(database_url)
+        .await
+}
+
+// sqlx already refuses unknown or modified migrations, this also catches a hand-made schema
+pub async fn run_migrations(pool: &Pool<Postgres>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let schema = sqlx::query!(
+        r#"
+        SELECT
+            to_regclass('_sqlx_migrations') IS NOT NULL as "tracked!",
+            to_regclass('reviews') IS NOT NULL as "has_reviews!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if !schema.tracked && schema.has_reviews {
+        return Err("Database contains a reviews table that was not created by migrations, refusing to start".into());
+    }
+
+    MIGRATOR.run(pool).await?;
+
+    let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0);
+    info!("Database schema is at version {}", latest);
+    Ok(())
+}
+
+// Recounts review_aggregates from scratch, for drift the write paths cannot see (such as expired blacklist entries)
+pub async fn rebuild_aggregates(pool: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("TRUNCATE review_aggregates").execute(&mut *tx).await?;
+
+    let rebuilt = sqlx::query!(
+        r#"
+        INSERT INTO review_aggregates (
+            target_id, review_type, review_count, rating_sum,
+            stars_1, stars_2, stars_3, stars_4, stars_5, last_review_at
+        )
+        SELECT target_id, review_type, COUNT(*), SUM(rating),
+            COUNT(*) FILTER (WHERE rating = 1),
+            COUNT(*) FILTER (WHERE rating = 2),
+            COUNT(*) FILTER (WHERE rating = 3),
+            COUNT(*) FILTER (WHERE rating = 4),
+            COUNT(*) FILTER (WHERE rating = 5),
+            MAX(created_at)
+        FROM visible_reviews
+        WHERE status = 'published'
+        GROUP BY target_id, review_type
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!("TRUNCATE criteria_aggregates").execute(&mut *tx).await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO criteria_aggregates (target_id, review_type, criterion, rating_count, rating_sum)
+        SELECT target_id, review_type, criteria.key, COUNT(*), SUM(criteria.value::int)
+        FROM visible_reviews, jsonb_each_text(visible_reviews.criteria) as criteria
+        WHERE status = 'published'
+        GROUP BY target_id, review_type, criteria.key
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!("Rebuilt rating aggregates for {} targets", rebuilt.rows_affected());
+    Ok(rebuilt.rows_affected())
+}
+
+// Must run in the transaction that changed the reviews. Locking the row first means a
+// concurrent writer recounts after this one commits and sees its changes.
+async fn recompute_aggregate(
+    conn: &mut PgConnection,
+    target_id: i64,
+    review_type: &ReviewType,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO review_aggregates (target_id, review_type)
+        VALUES ($1, $2)
+        ON CONFLICT (target_id, review_type)
+        DO UPDATE SET review_count = review_aggregates.review_count
+        "#,
+        target_id,
+        review_type as &ReviewType
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE review_aggregates
+        SET review_count = totals.review_count,
+            rating_sum = totals.rating_sum,
+            stars_1 = totals.stars_1,
+            stars_2 = totals.stars_2,
+            stars_3 = totals.stars_3,
+            stars_4 = totals.stars_4,
+            stars_5 = totals.stars_5,
+            last_review_at = totals.last_review_at
+        FROM (
+            SELECT COUNT(*) as review_count,
+                COALESCE(SUM(rating), 0) as rating_sum,
+                COUNT(*) FILTER (WHERE rating = 1) as stars_1,
+                COUNT(*) FILTER (WHERE rating = 2) as stars_2,
+                COUNT(*) FILTER (WHERE rating = 3) as stars_3,
+                COUNT(*) FILTER (WHERE rating = 4) as stars_4,
+                COUNT(*) FILTER (WHERE rating = 5) as stars_5,
+                MAX(created_at) as last_review_at
+            FROM visible_reviews
+            WHERE target_id = $1 AND review_type = $2 AND status = 'published'
+        ) totals
+        WHERE review_aggregates.target_id = $1 AND review_aggregates.review_type = $2
+        "#,
+        target_id,
+        review_type as &ReviewType
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Rows are zeroed rather than deleted so the cache refresh still sees criteria that lost their last rating
+    sqlx::query!(
+        r#"
+        UPDATE criteria_aggregates SET rating_count = 0, rating_sum = 0
+        WHERE target_id = $1 AND review_type = $2
+        "#,
+        target_id,
+        review_type as &ReviewType
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO criteria_aggregates (target_id, review_type, criterion, rating_count, rating_sum)
+        SELECT $1, $2, criteria.key, COUNT(*), SUM(criteria.value::int)
+        FROM visible_reviews, jsonb_each_text(visible_reviews.criteria) as criteria
+        WHERE target_id = $1 AND review_type = $2 AND status = 'published'
+        GROUP BY criteria.key
+        ON CONFLICT (target_id, review_type, criterion)
+        DO UPDATE SET rating_count = EXCLUDED.rating_count, rating_sum = EXCLUDED.rating_sum
+        "#,
+        target_id,
+        review_type as &ReviewType
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn reviewed_targets(conn: &mut PgConnection, reviewer_ids: &[i64]) -> Result<Vec<(i64, ReviewType)>, sqlx::Error> {
+    let targets = sqlx::query!(
+        r#"
+        SELECT DISTINCT target_id, review_type as "review_type: ReviewType"
+        FROM reviews
+        WHERE reviewer_id = ANY($1)
+        "#,
+        reviewer_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(targets.into_iter().map(|t| (t.target_id, t.review_type)).collect())
+}
+
+struct RatingSummary {
+    average: Option<f64>,
+    count: i64,
+}
+
+#[derive(Clone)]
+pub struct Database {
+    pool: Pool<Postgres>,
+    cache: Cache,
+    flights: SingleFlight,
+    // How long the last summary query took, in milliseconds
+    recompute_ms: Arc<AtomicU64>,
+}
+
+impl Database {
+    pub async fn new(database_url: &str, cache: Cache) -> Result<Self, sqlx::Error> {
+        let pool = connect(database_url).await?;
+
+        Ok(Self {
+            pool,
+            cache,
+            flights: SingleFlight::default(),
+            recompute_ms: Arc::new(AtomicU64::new(DEFAULT_RECOMPUTE_MS)),
+        })
+    }
+
+    pub async fn migrate(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        run_migrations(&self.pool).await
+    }
+}
+
+impl ReviewStore for Database {
+    async fn upsert_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        rating: i32,
+        comment: Option<String>,
+        criteria: BTreeMap<String, i32>,
+        review_type: &ReviewType,
+        status: ReviewStatus,
+    ) -> Result<(Review, bool), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // A soft-deleted row is revived in place, which counts as a new review for the caller
+        let row = sqlx::query!(
+            r#"
+            WITH previous AS (
+                SELECT deleted_at IS NOT NULL as was_deleted
+                FROM reviews
+                WHERE target_id = $1 AND reviewer_id = $2 AND review_type = $5
+            ), upserted AS (
+                INSERT INTO reviews (target_id, reviewer_id, rating, comment, review_type, status, criteria)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT ON CONSTRAINT reviews_target_reviewer_type_key
+                DO UPDATE SET
+                    rating = EXCLUDED.rating,
+                    comment = EXCLUDED.comment,
+                    criteria = EXCLUDED.criteria,
+                    created_at = CASE
+                        WHEN reviews.deleted_at IS NOT NULL THEN CURRENT_TIMESTAMP
+                        ELSE reviews.created_at
+                    END,
+                    updated_at = CASE
+                        WHEN reviews.deleted_at IS NOT NULL THEN NULL
+                        WHEN (reviews.rating, reviews.comment, reviews.criteria)
+                            IS DISTINCT FROM (EXCLUDED.rating, EXCLUDED.comment, EXCLUDED.criteria)
+                        THEN CURRENT_TIMESTAMP
+                        ELSE reviews.updated_at
+                    END,
+                    status = CASE
+                        WHEN reviews.deleted_at IS NULL AND reviews.status = 'hidden' THEN reviews.status
+                        ELSE EXCLUDED.status
+                    END,
+                    -- A revived review starts without the answer to the one that was deleted
+                    reply = CASE WHEN reviews.deleted_at IS NOT NULL THEN NULL ELSE reviews.reply END,
+                    reply_by = CASE WHEN reviews.deleted_at IS NOT NULL THEN NULL ELSE reviews.reply_by END,
+                    replied_at = CASE WHEN reviews.deleted_at IS NOT NULL THEN NULL ELSE reviews.replied_at END,
+                    deleted_at = NULL,
+                    deleted_by = NULL,
+                    delete_reason = NULL
+                RETURNING id, target_id, reviewer_id, rating, comment, review_type,
+                    created_at, updated_at, criteria, helpful_votes, unhelpful_votes,
+                    reply, reply_by, replied_at, (xmax = 0) as inserted
+            )
+            SELECT id as "id!", target_id as "target_id!", reviewer_id as "reviewer_id!",
+                rating as "rating!", comment,
+                review_type as "review_type!: ReviewType",
+                created_at,
+                updated_at,
+                criteria as "criteria!: sqlx::types::Json<BTreeMap<String, i32>>",
+                helpful_votes as "helpful_votes!",
+                unhelpful_votes as "unhelpful_votes!",
+                reply,
+                reply_by,
+                replied_at,
+                (inserted OR COALESCE((SELECT was_deleted FROM previous), false)) as "created!"
+            FROM upserted
+            "#,
+            target_id,
+            reviewer_id,
+            rating,
+            comment,
+            review_type as &ReviewType,
+            status as ReviewStatus,
+            sqlx::types::Json(&criteria) as _
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        recompute_aggregate(&mut tx, target_id, review_type).await?;
+        tx.commit().await?;
+
+        self.refresh_summary(target_id, review_type).await;
+
+        let review = Review {
+            id: row.id,
+            target_id: row.target_id,
+            reviewer_id: row.reviewer_id,
+            rating: row.rating,
+            comment: row.comment,
+            review_type: row.review_type,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            criteria: row.criteria,
+            helpful_votes: row.helpful_votes,
+            unhelpful_votes: row.unhelpful_votes,
+            reply: row.reply,
+            reply_by: row.reply_by,
+            replied_at: row.replied_at,
+        };
+
+        Ok((review, row.created))
+    }
+
+    async fn get_average_rating(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<Option<f64>, sqlx::Error> {
+        if let Ok(Some(rating)) = self.cache.get_rating(target_id, review_type.as_str()).await {
+            if !self.should_refresh_early(rating.ttl) {
+                return Ok(Some(rating.value));
+            }
+            return Ok(self.load_summary(target_id, review_type, true).await?.average);
+        }
+
+        Ok(self.load_summary(target_id, review_type, false).await?.average)
+    }
+
+    async fn get_reviews_count(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<i64, sqlx::Error> {
+        if let Ok(Some(count)) = self.cache.get_count(target_id, review_type.as_str()).await {
+            if !self.should_refresh_early(count.ttl) {
+                return Ok(count.value);
+            }
+            return Ok(self.load_summary(target_id, review_type, true).await?.count);
+        }
+
+        Ok(self.load_summary(target_id, review_type, false).await?.count)
+    }
+
+    async fn get_rating_distribution(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<RatingDistribution, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT stars_1, stars_2, stars_3, stars_4, stars_5
+            FROM review_aggregates
+            WHERE target_id = $1 AND review_type = $2
+            "#,
+            target_id,
+            review_type as &ReviewType
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row
+            .map(|r| RatingDistribution::from_counts([r.stars_1, r.stars_2, r.stars_3, r.stars_4, r.stars_5]))
+            .unwrap_or_default())
+    }
+
+    async fn has_reviewed(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 
+                FROM reviews 
+                WHERE target_id = $1 AND reviewer_id = $2 AND review_type = $3 AND deleted_at IS NULL
+            ) as exists
+            "#,
+            target_id,
+            reviewer_id,
+            review_type as &ReviewType
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.exists.unwrap_or(false))
+    }
+
+    async fn get_paginated_reviews(
+        &self,
+        target_id: i64,
+        page: i64,
+        per_page: i64,
+        review_type: &ReviewType,
+        query: &ReviewQuery,
+    ) -> Result<Vec<Review>, sqlx::Error> {
+        // Only the CASE matching the sort orders anything, the others are NULL for every row
+        sqlx::query_as!(
+            Review,
+            r#"
+            SELECT id as "id!", target_id as "target_id!", reviewer_id as "reviewer_id!", rating as "rating!", comment,
+                review_type as "review_type!: ReviewType",
+                created_at,
+                updated_at,
+                criteria as "criteria!: sqlx::types::Json<BTreeMap<String, i32>>",
+                helpful_votes as "helpful_votes!",
+                unhelpful_votes as "unhelpful_votes!",
+                reply,
+                reply_by,
+                replied_at
+            FROM visible_reviews
+            WHERE target_id = $1 AND review_type = $2 AND status = 'published'
+                AND ($6::INTEGER IS NULL OR rating >= $6)
+                AND ($7::INTEGER IS NULL OR rating <= $7)
+                AND ($8::BOOLEAN IS NULL OR (COALESCE(comment, '') <> '') = $8)
+                AND ($9::TIMESTAMPTZ IS NULL OR created_at >= $9)
+                AND ($10::TIMESTAMPTZ IS NULL OR created_at < $10)
+            ORDER BY
+                CASE WHEN $5 = 'helpful' THEN helpful_votes - unhelpful_votes END DESC,
+                CASE WHEN $5 = 'highest' THEN rating END DESC,
+                CASE WHEN $5 = 'lowest' THEN rating END ASC,
+                CASE WHEN $5 = 'oldest' THEN created_at END ASC,
+                created_at DESC,
+                id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            target_id,
+            review_type as &ReviewType,
+            per_page,
+            page.saturating_mul(per_page),
+            query.sort.as_str(),
+            query.min_rating,
+            query.max_rating,
+            query.has_comment,
+            query.since,
+            query.until
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_matching_reviews(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        query: &ReviewQuery,
+    ) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM visible_reviews
+            WHERE target_id = $1 AND review_type = $2 AND status = 'published'
+                AND ($3::INTEGER IS NULL OR rating >= $3)
+                AND ($4::INTEGER IS NULL OR rating <= $4)
+                AND ($5::BOOLEAN IS NULL OR (COALESCE(comment, '') <> '') = $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at >= $6)
+                AND ($7::TIMESTAMPTZ IS NULL OR created_at < $7)
+            "#,
+            target_id,
+            review_type as &ReviewType,
+            query.min_rating,
+            query.max_rating,
+            query.has_comment,
+            query.since,
+            query.until
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.count)
+    }
+
+    async fn get_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<Option<Review>, sqlx::Error> {
+        sqlx::query_as!(
+            Review,
+            r#"
+            SELECT id as "id!", target_id as "target_id!", reviewer_id as "reviewer_id!", rating as "rating!", comment,
+                review_type as "review_type!: ReviewType",
+                created_at,
+                updated_at,
+                criteria as "criteria!: sqlx::types::Json<BTreeMap<String, i32>>",
+                helpful_votes as "helpful_votes!",
+                unhelpful_votes as "unhelpful_votes!",
+                reply,
+                reply_by,
+                replied_at
+            FROM visible_reviews
+            WHERE target_id = $1 AND reviewer_id = $2 AND review_type = $3
+            "#,
+            target_id,
+            reviewer_id,
+            review_type as &ReviewType
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn get_review_revisions(&self, review_id: i32) -> Result<Vec<ReviewRevision>, sqlx::Error> {
+        sqlx::query_as!(
+            ReviewRevision,
+            r#"
+            SELECT id, review_id, rating, comment, written_at, replaced_at as "replaced_at?"
+            FROM review_revisions
+            WHERE review_id = $1
+            ORDER BY replaced_at DESC
+            "#,
+            review_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn delete_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+        deleted_by: i64,
+        reason: Option<String>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE reviews
+            SET deleted_at = CURRENT_TIMESTAMP, deleted_by = $4, delete_reason = $5
+            WHERE target_id = $1 AND reviewer_id = $2 AND review_type = $3 AND deleted_at IS NULL
+            "#,
+            target_id,
+            reviewer_id,
+            review_type as &ReviewType,
+            deleted_by,
+            reason
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        recompute_aggregate(&mut tx, target_id, review_type).await?;
+        tx.commit().await?;
+
+        self.refresh_summary(target_id, review_type).await;
+
+        Ok(true)
+    }
+
+    async fn get_review_by_id(&self, review_id: i32) -> Result<Option<Review>, sqlx::Error> {
+        sqlx::query_as!(
+            Review,
+            r#"
+            SELECT id, target_id, reviewer_id, rating, comment,
+                review_type as "review_type: ReviewType",
+                created_at,
+                updated_at,
+                criteria as "criteria: sqlx::types::Json<BTreeMap<String, i32>>",
+                helpful_votes,
+                unhelpful_votes,
+                reply,
+                reply_by,
+                replied_at
+            FROM reviews
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            review_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn get_review_status(&self, review_id: i32) -> Result<Option<ReviewStatus>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT status as "status: ReviewStatus"
+            FROM reviews
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            review_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn report_review(
+        &self,
+        review_id: i32,
+        reporter_id: i64,
+        reason: String,
+    ) -> Result<Option<ReviewReport>, sqlx::Error> {
+        sqlx::query_as!(
+            ReviewReport,
+            r#"
+            INSERT INTO review_reports (review_id, reporter_id, reason)
+            SELECT id, $2, $3
+            FROM reviews
+            WHERE id = $1 AND deleted_at IS NULL AND status = 'published'
+            ON CONFLICT ON CONSTRAINT review_reports_review_reporter_key DO NOTHING
+            RETURNING id, review_id, reporter_id, reason, created_at,
+                resolution as "resolution: ReportResolution",
+                resolved_by,
+                resolved_at
+            "#,
+            review_id,
+            reporter_id,
+            reason
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn resolve_report(
+        &self,
+        report_id: i32,
+        moderator_id: i64,
+        resolution: ReportResolution,
+    ) -> Result<Option<ReviewReport>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let report = sqlx::query_as!(
+            ReviewReport,
+            r#"
+            UPDATE review_reports
+            SET resolution = $2, resolved_by = $3, resolved_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND resolution IS NULL
+            RETURNING id, review_id, reporter_id, reason, created_at,
+                resolution as "resolution: ReportResolution",
+                resolved_by,
+                resolved_at
+            "#,
+            report_id,
+            &resolution as &ReportResolution,
+            moderator_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let report = match report {
+            Some(report) => report,
+            None => return Ok(None),
+        };
+
+        let target = match resolution {
+            ReportResolution::Dismissed => None,
+            ReportResolution::Hidden => sqlx::query!(
+                r#"
+                UPDATE reviews
+                SET status = 'hidden'
+                WHERE id = $1
+                RETURNING target_id, review_type as "review_type: ReviewType"
+                "#,
+                report.review_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|r| (r.target_id, r.review_type)),
+            ReportResolution::Deleted => sqlx::query!(
+                r#"
+                UPDATE reviews
+                SET deleted_at = CURRENT_TIMESTAMP, deleted_by = $2, delete_reason = 'Removed after report'
+                WHERE id = $1 AND deleted_at IS NULL
+                RETURNING target_id, review_type as "review_type: ReviewType"
+                "#,
+                report.review_id,
+                moderator_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|r| (r.target_id, r.review_type)),
+        };
+
+        if let Some((target_id, review_type)) = &target {
+            recompute_aggregate(&mut tx, *target_id, review_type).await?;
+        }
+
+        // Acting on the review settles every other open report against it as well
+        if target.is_some() {
+            sqlx::query!(
+                r#"
+                UPDATE review_reports
+                SET resolution = $2, resolved_by = $3, resolved_at = CURRENT_TIMESTAMP
+                WHERE review_id = $1 AND resolution IS NULL
+                "#,
+                report.review_id,
+                &resolution as &ReportResolution,
+                moderator_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        if let Some((target_id, review_type)) = target {
+            self.refresh_summary(target_id, &review_type).await;
+        }
+
+        Ok(Some(report))
+    }
+
+    async fn resolve_pending_review(
+        &self,
+        review_id: i32,
+        moderator_id: i64,
+        approve: bool,
+    ) -> Result<Option<Review>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let review = if approve {
+            sqlx::query_as!(
+                Review,
+                r#"
+                UPDATE reviews
+                SET status = 'published'
+                WHERE id = $1 AND status = 'pending' AND deleted_at IS NULL
+                RETURNING id, target_id, reviewer_id, rating, comment,
+                    review_type as "review_type: ReviewType",
+                    created_at,
+                    updated_at,
+                    criteria as "criteria: sqlx::types::Json<BTreeMap<String, i32>>",
+                    helpful_votes,
+                    unhelpful_votes,
+                    reply,
+                    reply_by,
+                    replied_at
+                "#,
+                review_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        } else {
+            sqlx::query_as!(
+                Review,
+                r#"
+                UPDATE reviews
+                SET deleted_at = CURRENT_TIMESTAMP, deleted_by = $2, delete_reason = 'Rejected by moderation'
+                WHERE id = $1 AND status = 'pending' AND deleted_at IS NULL
+                RETURNING id, target_id, reviewer_id, rating, comment,
+                    review_type as "review_type: ReviewType",
+                    created_at,
+                    updated_at,
+                    criteria as "criteria: sqlx::types::Json<BTreeMap<String, i32>>",
+                    helpful_votes,
+                    unhelpful_votes,
+                    reply,
+                    reply_by,
+                    replied_at
+                "#,
+                review_id,
+                moderator_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        };
+
+        if let Some(review) = &review {
+            recompute_aggregate(&mut tx, review.target_id, &review.review_type).await?;
+        }
+        tx.commit().await?;
+
+        if let Some(review) = &review {
+            self.refresh_summary(review.target_id, &review.review_type).await;
+        }
+
+        Ok(review)
+    }
+
+    async fn blacklist_user(
+        &self,
+        user_id: i64,
+        scope: BlacklistScope,
+        reason: Option<String>,
+        expires_at: Option<OffsetDateTime>,
+        created_by: i64,
+    ) -> Result<BlacklistEntry, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let entry = sqlx::query_as!(
+            BlacklistEntry,
+            r#"
+            INSERT INTO blacklist (user_id, scope, reason, expires_at, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT ON CONSTRAINT blacklist_user_scope_key
+            DO UPDATE SET
+                reason = EXCLUDED.reason,
+                expires_at = EXCLUDED.expires_at,
+                created_by = EXCLUDED.created_by,
+                created_at = CURRENT_TIMESTAMP
+            RETURNING id, user_id, scope as "scope: BlacklistScope", reason, created_by, created_at, expires_at
+            "#,
+            user_id,
+            &scope as &BlacklistScope,
+            reason,
+            expires_at,
+            created_by
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if scope == BlacklistScope::Everywhere {
+            self.recount_reviewed_targets(tx, &[user_id]).await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok(entry)
+    }
+
+    async fn unblacklist_user(&self, user_id: i64, scope: BlacklistScope) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM blacklist
+            WHERE user_id = $1 AND scope = $2
+            "#,
+            user_id,
+            &scope as &BlacklistScope
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() > 0 && scope == BlacklistScope::Everywhere {
+            self.recount_reviewed_targets(tx, &[user_id]).await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn purge_expired_blacklist(&self) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let expired = sqlx::query!(
+            r#"
+            DELETE FROM blacklist
+            WHERE expires_at <= CURRENT_TIMESTAMP
+            RETURNING user_id, scope as "scope: BlacklistScope"
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Reviews hidden by an expired entry are visible again, so their targets' aggregates are stale
+        let unhidden: Vec<i64> = expired
+            .iter()
+            .filter(|e| e.scope == BlacklistScope::Everywhere)
+            .map(|e| e.user_id)
+            .collect();
+        self.recount_reviewed_targets(tx, &unhidden).await?;
+
+        Ok(expired.len() as u64)
+    }
+
+    async fn get_active_blacklist(&self, user_id: i64) -> Result<Vec<BlacklistEntry>, sqlx::Error> {
+        sqlx::query_as!(
+            BlacklistEntry,
+            r#"
+            SELECT id, user_id, scope as "scope: BlacklistScope", reason, created_by, created_at, expires_at
+            FROM blacklist
+            WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn get_review_activity(
+        &self,
+        since: OffsetDateTime,
+        min_reviews: i64,
+    ) -> Result<Vec<TargetActivity>, sqlx::Error> {
+        sqlx::query_as!(
+            TargetActivity,
+            r#"
+            SELECT target_id as "target_id!", review_type as "review_type!: ReviewType",
+                COUNT(*) FILTER (WHERE created_at >= $1) as "recent_reviews!",
+                AVG(rating::float) FILTER (WHERE created_at >= $1) as "recent_average!",
+                AVG(rating::float) FILTER (WHERE created_at < $1) as baseline_average,
+                ARRAY_AGG(reviewer_id) FILTER (WHERE created_at >= $1) as "recent_reviewers!"
+            FROM visible_reviews
+            WHERE status = 'published'
+            GROUP BY target_id, review_type
+            HAVING COUNT(*) FILTER (WHERE created_at >= $1) >= $2
+            "#,
+            since,
+            min_reviews
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn flag_target(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        reason: String,
+        frozen_average: Option<f64>,
+        since: OffsetDateTime,
+        expires_at: OffsetDateTime,
+    ) -> Result<Option<TargetFlag>, sqlx::Error> {
+        let flag = sqlx::query_as!(
+            TargetFlag,
+            r#"
+            INSERT INTO target_flags (target_id, review_type, reason, frozen_average, expires_at)
+            VALUES ($1, $2, $3, $4, $6)
+            ON CONFLICT ON CONSTRAINT target_flags_target_type_key
+            DO UPDATE SET
+                reason = EXCLUDED.reason,
+                frozen_average = EXCLUDED.frozen_average,
+                flagged_at = CURRENT_TIMESTAMP,
+                expires_at = EXCLUDED.expires_at,
+                cleared_by = NULL,
+                cleared_at = NULL
+            WHERE target_flags.cleared_at < $5
+                OR (target_flags.cleared_at IS NULL AND target_flags.expires_at <= CURRENT_TIMESTAMP)
+            RETURNING id, target_id, review_type as "review_type: ReviewType", reason, frozen_average,
+                flagged_at, expires_at as "expires_at?"
+            "#,
+            target_id,
+            review_type as &ReviewType,
+            reason,
+            frozen_average,
+            since,
+            expires_at
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if flag.is_some() {
+            self.refresh_summary(target_id, review_type).await;
+        }
+
+        Ok(flag)
+    }
+
+    async fn get_target_flag(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<Option<TargetFlag>, sqlx::Error> {
+        sqlx::query_as!(
+            TargetFlag,
+            r#"
+            SELECT id, target_id, review_type as "review_type: ReviewType", reason, frozen_average,
+                flagged_at, expires_at as "expires_at?"
+            FROM target_flags
+            WHERE target_id = $1 AND review_type = $2 AND cleared_at IS NULL
+                AND expires_at > CURRENT_TIMESTAMP
+            "#,
+            target_id,
+            review_type as &ReviewType
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn clear_target_flag(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        moderator_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE target_flags
+            SET cleared_by = $3, cleared_at = CURRENT_TIMESTAMP
+            WHERE target_id = $1 AND review_type = $2 AND cleared_at IS NULL
+                AND expires_at > CURRENT_TIMESTAMP
+            "#,
+            target_id,
+            review_type as &ReviewType,
+            moderator_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.refresh_summary(target_id, review_type).await;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_global_average(&self, review_type: &ReviewType) -> Result<Option<f64>, sqlx::Error> {
+        // Cached under id 0 of its own prefix, a prior a few minutes old is still a fine prior
+        let prefix = format!("prior_{}", review_type.as_str());
+        if let Ok(Some(prior)) = self.cache.get_rating(0, &prefix).await {
+            return Ok(Some(prior.value));
+        }
+
+        let average = sqlx::query_scalar!(
+            r#"
+            SELECT SUM(rating_sum)::float / NULLIF(SUM(review_count), 0)
+            FROM review_aggregates
+            WHERE review_type = $1
+            "#,
+            review_type as &ReviewType
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if let Some(average) = average {
+            let _ = self.cache.cache_rating(0, average, &prefix).await;
+        }
+
+        Ok(average)
+    }
+
+    async fn get_rated_targets(&self, review_type: &ReviewType) -> Result<Vec<RatedTarget>, sqlx::Error> {
+        sqlx::query_as!(
+            RatedTarget,
+            r#"
+            SELECT target_id, review_count, rating_sum::float / review_count as "average!"
+            FROM review_aggregates
+            WHERE review_type = $1 AND review_count > 0
+                AND NOT EXISTS (
+                    SELECT 1 FROM target_flags
+                    WHERE target_flags.target_id = review_aggregates.target_id
+                        AND target_flags.review_type = review_aggregates.review_type
+                        AND target_flags.cleared_at IS NULL
+                        AND target_flags.expires_at > CURRENT_TIMESTAMP
+                )
+            "#,
+            review_type as &ReviewType
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn get_weighted_rating(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        since: Option<OffsetDateTime>,
+        half_life: Option<time::Duration>,
+    ) -> Result<WeightedRating, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(
+                    (
+                        SELECT frozen_average
+                        FROM target_flags
+                        WHERE target_id = $1 AND review_type = $2 AND cleared_at IS NULL
+                            AND expires_at > CURRENT_TIMESTAMP
+                    ),
+                    SUM(rating * weight) / NULLIF(SUM(weight), 0)
+                ) as average,
+                COALESCE(SUM(weight), 0) as "weight!"
+            FROM (
+                SELECT rating,
+                    CASE WHEN $4::float8 IS NULL THEN 1.0
+                    ELSE power(
+                        0.5,
+                        EXTRACT(EPOCH FROM CURRENT_TIMESTAMP - COALESCE(created_at, CURRENT_TIMESTAMP))::float8 / $4
+                    )
+                    END as weight
+                FROM visible_reviews
+                WHERE target_id = $1 AND review_type = $2 AND status = 'published'
+                    AND ($3::timestamptz IS NULL OR created_at >= $3)
+            ) as weighted
+            "#,
+            target_id,
+            review_type as &ReviewType,
+            since,
+            half_life.map(|h| h.as_seconds_f64())
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(WeightedRating { average: row.average, weight: row.weight })
+    }
+
+    async fn get_criteria(
+        &self,
+        review_type: &ReviewType,
+        guild_id: Option<i64>,
+    ) -> Result<Vec<RatingCriterion>, sqlx::Error> {
+        sqlx::query_as!(
+            RatingCriterion,
+            r#"
+            SELECT key, label
+            FROM rating_criteria
+            WHERE review_type = $1 AND guild_id IS NOT DISTINCT FROM (
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM rating_criteria WHERE review_type = $1 AND guild_id = $2
+                ) THEN $2::bigint END
+            )
+            ORDER BY position, id
+            "#,
+            review_type as &ReviewType,
+            guild_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn set_guild_criteria(
+        &self,
+        review_type: &ReviewType,
+        guild_id: i64,
+        criteria: Vec<RatingCriterion>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM rating_criteria WHERE review_type = $1 AND guild_id = $2",
+            review_type as &ReviewType,
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for (position, criterion) in criteria.iter().enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO rating_criteria (review_type, guild_id, key, label, position)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                review_type as &ReviewType,
+                guild_id,
+                criterion.key,
+                criterion.label,
+                position as i32
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn get_criteria_averages(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        keys: &[String],
+    ) -> Result<BTreeMap<String, f64>, sqlx::Error> {
+        let mut averages = BTreeMap::new();
+        for key in keys {
+            match self.cache.get_rating(target_id, &criterion_prefix(review_type, key)).await {
+                Ok(Some(rating)) => {
+                    averages.insert(key.clone(), rating.value);
+                }
+                // One miss is enough to read them all, the read caches every criterion at once
+                _ => {
+                    let loaded = self.load_criteria(target_id, review_type).await?;
+                    return Ok(loaded.into_iter().filter(|(key, _)| keys.contains(key)).collect());
+                }
+            }
+        }
+
+        Ok(averages)
+    }
+
+    async fn vote_review(
+        &self,
+        review_id: i32,
+        voter_id: i64,
+        helpful: bool,
+    ) -> Result<Option<VoteTally>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Concurrent voters queue on the review row, so each recount sees the votes before it
+        let exists = sqlx::query_scalar!(
+            "SELECT id FROM reviews WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+            review_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if exists.is_none() {
+            return Ok(None);
+        }
+
+        let previous = sqlx::query_scalar!(
+            "SELECT helpful FROM review_votes WHERE review_id = $1 AND voter_id = $2",
+            review_id,
+            voter_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Voting the same way twice takes the vote back
+        let vote = if previous == Some(helpful) {
+            sqlx::query!(
+                "DELETE FROM review_votes WHERE review_id = $1 AND voter_id = $2",
+                review_id,
+                voter_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            None
+        } else {
+            sqlx::query!(
+                r#"
+                INSERT INTO review_votes (review_id, voter_id, helpful)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (review_id, voter_id)
+                DO UPDATE SET helpful = EXCLUDED.helpful, voted_at = CURRENT_TIMESTAMP
+                "#,
+                review_id,
+                voter_id,
+                helpful
+            )
+            .execute(&mut *tx)
+            .await?;
+            Some(helpful)
+        };
+
+        let counts = sqlx::query!(
+            r#"
+            UPDATE reviews
+            SET helpful_votes = (SELECT COUNT(*) FROM review_votes WHERE review_id = $1 AND helpful),
+                unhelpful_votes = (SELECT COUNT(*) FROM review_votes WHERE review_id = $1 AND NOT helpful)
+            WHERE id = $1
+            RETURNING helpful_votes, unhelpful_votes
+            "#,
+            review_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(VoteTally {
+            helpful_votes: counts.helpful_votes,
+            unhelpful_votes: counts.unhelpful_votes,
+            vote,
+        }))
+    }
+
+    async fn react_to_review(&self, review_id: i32, user_id: i64, emoji: String) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO review_reactions (review_id, user_id, emoji)
+            SELECT id, $2, $3 FROM reviews WHERE id = $1 AND deleted_at IS NULL
+            ON CONFLICT (review_id, user_id)
+            DO UPDATE SET emoji = EXCLUDED.emoji, reacted_at = CURRENT_TIMESTAMP
+            "#,
+            review_id,
+            user_id,
+            emoji
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_review_reactions(&self, review_id: i32) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT emoji, COUNT(*) as "count!"
+            FROM review_reactions
+            WHERE review_id = $1
+            GROUP BY emoji
+            ORDER BY COUNT(*) DESC, emoji
+            "#,
+            review_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.emoji, row.count)).collect())
+    }
+
+    async fn reply_to_review(
+        &self,
+        review_id: i32,
+        author_id: i64,
+        reply: Option<String>,
+    ) -> Result<Option<Review>, sqlx::Error> {
+        sqlx::query_as!(
+            Review,
+            r#"
+            UPDATE reviews
+            SET reply = $3,
+                reply_by = CASE WHEN $3::text IS NULL THEN NULL ELSE $2::BIGINT END,
+                replied_at = CASE WHEN $3::text IS NULL THEN NULL ELSE CURRENT_TIMESTAMP END
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, target_id, reviewer_id, rating, comment,
+                review_type as "review_type: ReviewType",
+                created_at,
+                updated_at,
+                criteria as "criteria: sqlx::types::Json<BTreeMap<String, i32>>",
+                helpful_votes,
+                unhelpful_votes,
+                reply,
+                reply_by,
+                replied_at
+            "#,
+            review_id,
+            author_id,
+            reply
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn get_reply_role(&self, guild_id: i64) -> Result<Option<i64>, sqlx::Error> {
+        let role = sqlx::query_scalar!(
+            "SELECT reply_role FROM guild_settings WHERE guild_id = $1",
+            guild_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(role.flatten())
+    }
+
+    async fn set_reply_role(&self, guild_id: i64, role_id: Option<i64>, updated_by: i64) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO guild_settings (guild_id, reply_role, updated_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET reply_role = EXCLUDED.reply_role, updated_by = EXCLUDED.updated_by,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            guild_id,
+            role_id,
+            updated_by
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn create_api_key(
+        &self,
+        name: String,
+        key_prefix: String,
+        key_hash: String,
+        scopes: Vec<String>,
+        created_by: i64,
+    ) -> Result<ApiKey, sqlx::Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            INSERT INTO api_keys (name, key_prefix, key_hash, scopes, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, name, key_prefix, scopes, created_by, created_at, last_used_at, revoked_at
+            "#,
+            name,
+            key_prefix,
+            key_hash,
+            &scopes,
+            created_by
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn revoke_api_key(&self, key_id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE api_keys SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1 AND revoked_at IS NULL",
+            key_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT id, name, key_prefix, scopes, created_by, created_at, last_used_at, revoked_at
+            FROM api_keys
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn authenticate_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP
+            WHERE key_hash = $1 AND revoked_at IS NULL
+            RETURNING id, name, key_prefix, scopes, created_by, created_at, last_used_at, revoked_at
+            "#,
+            key_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn record_api_write(
+        &self,
+        api_key_id: i32,
+        action: &str,
+        review_id: Option<i32>,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO api_key_writes (api_key_id, action, review_id, target_id, reviewer_id, review_type)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            api_key_id,
+            action,
+            review_id,
+            target_id,
+            reviewer_id,
+            review_type as &ReviewType
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_score_view(&self, guild_id: i64) -> Result<Option<ScoreView>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT score_view as "score_view: ScoreView" FROM guild_settings WHERE guild_id = $1"#,
+            guild_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn set_score_view(&self, guild_id: i64, view: ScoreView, updated_by: i64) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO guild_settings (guild_id, score_view, updated_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET score_view = EXCLUDED.score_view, updated_by = EXCLUDED.updated_by,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            guild_id,
+            view as ScoreView,
+            updated_by
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl Database {
+    // Hiding a user everywhere changes the totals of every target they reviewed
+    async fn recount_reviewed_targets(
+        &self,
+        mut tx: sqlx::Transaction<'_, Postgres>,
+        reviewer_ids: &[i64],
+    ) -> Result<(), sqlx::Error> {
+        let targets = reviewed_targets(&mut tx, reviewer_ids).await?;
+        for (target_id, review_type) in &targets {
+            recompute_aggregate(&mut tx, *target_id, review_type).await?;
+        }
+        tx.commit().await?;
+
+        let keys: Vec<(i64, &str)> = targets
+            .iter()
+            .map(|(target_id, review_type)| (*target_id, review_type.as_str()))
+            .collect();
+        let _ = self.cache.invalidate_many(&keys).await;
+        for (target_id, review_type) in &targets {
+            let _ = self.load_criteria(*target_id, review_type).await;
+        }
+
+        Ok(())
+    }
+
+    // XFetch: the closer a value is to expiring, relative to how long it takes to recompute,
+    // the likelier a reader refreshes it early, so one caller recomputes before everyone misses
+    fn should_refresh_early(&self, ttl: Duration) -> bool {
+        let delta = self.recompute_ms.load(Ordering::Relaxed) as f64 / 1000.0;
+        let roll = rand::random::<f64>().max(f64::MIN_POSITIVE);
+        delta * EARLY_REFRESH_BETA * -roll.ln() >= ttl.as_secs_f64()
+    }
+
+    // Recomputes the average and count of a target once, however many callers ask for it at the same time
+    async fn load_summary(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        force: bool,
+    ) -> Result<RatingSummary, sqlx::Error> {
+        let prefix = review_type.as_str();
+        let key = format!("{}:{}", prefix, target_id);
+
+        self.flights.run(&key, || async {
+            // Callers that waited on another one find its result in the cache
+            if !force {
+                let rating = self.cache.get_rating(target_id, prefix).await;
+                let count = self.cache.get_count(target_id, prefix).await;
+                if let (Ok(Some(rating)), Ok(Some(count))) = (rating, count) {
+                    return Ok(RatingSummary { average: Some(rating.value), count: count.value });
+                }
+            }
+
+            let started = Instant::now();
+            let summary = self.compute_summary(target_id, review_type).await?;
+            self.recompute_ms.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+
+            match summary.average {
+                Some(average) => {
+                    let _ = self.cache.cache_rating(target_id, average, prefix).await;
+                }
+                // The last review is gone, an older average must not outlive it
+                None => {
+                    let _ = self.cache.invalidate(target_id, prefix).await;
+                }
+            }
+            let _ = self.cache.cache_count(target_id, summary.count, prefix).await;
+
+            Ok(summary)
+        })
+        .await
+    }
+
+    async fn compute_summary(&self, target_id: i64, review_type: &ReviewType) -> Result<RatingSummary, sqlx::Error> {
+        // A target flagged for review bombing keeps the average it had before the burst
+        let result = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(
+                    (
+                        SELECT frozen_average
+                        FROM target_flags
+                        WHERE target_id = $1 AND review_type = $2 AND cleared_at IS NULL
+                            AND expires_at > CURRENT_TIMESTAMP
+                    ),
+                    aggregates.rating_sum::float / NULLIF(aggregates.review_count, 0)
+                ) as average,
+                COALESCE(aggregates.review_count, 0) as "count!"
+            FROM (SELECT 1) as target
+            LEFT JOIN review_aggregates aggregates
+                ON aggregates.target_id = $1 AND aggregates.review_type = $2
+            "#,
+            target_id,
+            review_type as &ReviewType
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(RatingSummary { average: result.average, count: result.count })
+    }
+
+    // Write-through: the new values replace the cached ones instead of leaving a miss behind
+    async fn refresh_summary(&self, target_id: i64, review_type: &ReviewType) {
+        if self.load_summary(target_id, review_type, true).await.is_err() {
+            let _ = self.cache.invalidate(target_id, review_type.as_str()).await;
+        }
+        let _ = self.load_criteria(target_id, review_type).await;
+    }
+
+    // Reads every criterion sub-average of the target and writes them through to the cache
+    async fn load_criteria(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<BTreeMap<String, f64>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT criterion, rating_sum::float / NULLIF(rating_count, 0) as average
+            FROM criteria_aggregates
+            WHERE target_id = $1 AND review_type = $2
+            "#,
+            target_id,
+            review_type as &ReviewType
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut averages = BTreeMap::new();
+        for row in rows {
+            let prefix = criterion_prefix(review_type, &row.criterion);
+            match row.average {
+                Some(average) => {
+                    let _ = self.cache.cache_rating(target_id, average, &prefix).await;
+                    averages.insert(row.criterion, average);
+                }
+                None => {
+                    let _ = self.cache.invalidate(target_id, &prefix).await;
+                }
+            }
+        }
+
+        Ok(averages)
+    }
+}
+
+fn criterion_prefix(review_type: &ReviewType, key: &str) -> String {
+    format!("{}_{}", review_type.as_str(), key)
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use moka::{sync::Cache as MokaCache, Expiry};
+use redis::RedisResult;
+use std::{
+    env,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use crate::app::utils::cache::{CacheBackend, Cached, CACHE_DURATION};
+
+const DEFAULT_CAPACITY: u64 = 10_000;
+
+#[derive(Clone)]
+struct Entry {
+    value: String,
+    expires: Instant,
+}
+
+struct EntryExpiry;
+
+impl Expiry<String, Entry> for EntryExpiry {
+    fn expire_after_create(&self, _key: &String, value: &Entry, created_at: Instant) -> Option<Duration> {
+        Some(value.expires.saturating_duration_since(created_at))
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &String,
+        value: &Entry,
+        updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(value.expires.saturating_duration_since(updated_at))
+    }
+}
+
+// Keeps the cache inside the process, for deployments that run a single instance without Redis
+pub struct MemoryCache {
+    entries: MokaCache<String, Entry>,
+    // Serialises read-modify-write updates of the rate limit counters
+    counters: Mutex<()>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: u64) -> Self {
+        let entries = MokaCache::builder()
+            .max_capacity(capacity)
+            .expire_after(EntryExpiry)
+            .build();
+
+        Self { entries, counters: Mutex::new(()) }
+    }
+
+    pub fn from_env() -> Self {
+        let capacity = env::var("CACHE_MEMORY_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        Self::new(capacity)
+    }
+
+    fn get(&self, key: &str) -> Option<Entry> {
+        self.entries.get(key).filter(|e| e.expires > Instant::now())
+    }
+
+    fn get_cached<T: std::str::FromStr>(&self, key: &str) -> Option<Cached<T>> {
+        let entry = self.get(key)?;
+        Some(Cached {
+            value: entry.value.parse().ok()?,
+            ttl: entry.expires.saturating_duration_since(Instant::now()),
+        })
+    }
+
+    fn put(&self, key: String, value: String, seconds: usize) {
+        let expires = Instant::now() + Duration::from_secs(seconds as u64);
+        self.entries.insert(key, Entry { value, expires });
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn cache_rating(&self, target_id: i64, average: f64, prefix: &str) -> RedisResult<()> {
+        self.put(format!("{}:{}:rating", prefix, target_id), average.to_string(), CACHE_DURATION);
+        Ok(())
+    }
+
+    async fn get_rating(&self, target_id: i64, prefix: &str) -> RedisResult<Option<Cached<f64>>> {
+        Ok(self.get_cached(&format!("{}:{}:rating", prefix, target_id)))
+    }
+
+    async fn cache_count(&self, target_id: i64, count: i64, prefix: &str) -> RedisResult<()> {
+        self.put(format!("{}:{}:count", prefix, target_id), count.to_string(), CACHE_DURATION);
+        Ok(())
+    }
+
+    async fn get_count(&self, target_id: i64, prefix: &str) -> RedisResult<Option<Cached<i64>>> {
+        Ok(self.get_cached(&format!("{}:{}:count", prefix, target_id)))
+    }
+
+    async fn invalidate_many(&self, targets: &[(i64, &str)]) -> RedisResult<()> {
+        for (target_id, prefix) in targets {
+            self.entries.invalidate(&format!("{}:{}:rating", prefix, target_id));
+            self.entries.invalidate(&format!("{}:{}:count", prefix, target_id));
+        }
+        Ok(())
+    }
+
+    async fn hit_window(&self, key: &str, window: usize) -> RedisResult<(i64, i64)> {
+        let _guard = self.counters.lock().unwrap();
+
+        let entry = match self.get(key) {
+            Some(entry) => Entry {
+                value: (entry.value.parse::<i64>().unwrap_or(0) + 1).to_string(),
+                expires: entry.expires,
+            },
+            None => Entry {
+                value: "1".to_string(),
+                expires: Instant::now() + Duration::from_secs(window as u64),
+            },
+        };
+        let count = entry.value.parse().unwrap_or(1);
+        let ttl = entry.expires.saturating_duration_since(Instant::now()).as_secs() as i64;
+        self.entries.insert(key.to_string(), entry);
+
+        Ok((count, ttl))
+    }
+
+    async fn release_window(&self, key: &str) -> RedisResult<()> {
+        let _guard = self.counters.lock().unwrap();
+
+        if let Some(entry) = self.get(key) {
+            let count = entry.value.parse::<i64>().unwrap_or(1);
+            self.entries.insert(key.to_string(), Entry {
+                value: (count - 1).max(0).to_string(),
+                expires: entry.expires,
+            });
+        }
+        Ok(())
+    }
+
+    async fn set_cooldown(&self, key: &str, seconds: usize) -> RedisResult<()> {
+        self.put(key.to_string(), "1".to_string(), seconds);
+        Ok(())
+    }
+
+    async fn claim_cooldown(&self, key: &str, seconds: usize) -> RedisResult<Option<i64>> {
+        let _guard = self.counters.lock().unwrap();
+
+        let remaining = self
+            .get(key)
+            .map(|e| e.expires.saturating_duration_since(Instant::now()).as_secs() as i64)
+            .filter(|seconds| *seconds > 0);
+        if remaining.is_none() {
+            self.put(key.to_string(), "1".to_string(), seconds);
+        }
+
+        Ok(remaining)
+    }
+}
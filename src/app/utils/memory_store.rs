@@ -0,0 +1,947 @@
+use sqlx::types::Json;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use time::{Duration, OffsetDateTime};
+use crate::app::{
+    models::{
+        api_key::ApiKey,
+        review::{
+            Review, ReviewRevision, ReviewStatus, ReviewType, ReviewReport, ReportResolution,
+            BlacklistEntry, BlacklistScope, TargetFlag, TargetActivity, RatingDistribution,
+            RatedTarget, ScoreView, WeightedRating, RatingCriterion, ReviewQuery, ReviewSort, VoteTally,
+        },
+    },
+    utils::store::ReviewStore,
+};
+
+// The soft-delete columns Postgres keeps on a review row, which the Review model does not carry
+#[derive(Debug, Clone)]
+pub struct Deletion {
+    pub deleted_at: OffsetDateTime,
+    pub deleted_by: i64,
+    pub reason: Option<String>,
+}
+
+#[derive(Default)]
+struct Inner {
+    reviews: Vec<Review>,
+    deletions: HashMap<i32, Deletion>,
+    revisions: Vec<ReviewRevision>,
+    reports: Vec<ReviewReport>,
+    statuses: HashMap<i32, ReviewStatus>,
+    blacklist: Vec<BlacklistEntry>,
+    flags: Vec<(TargetFlag, Option<OffsetDateTime>)>,
+    score_views: HashMap<i64, ScoreView>,
+    guild_criteria: HashMap<(&'static str, i64), Vec<RatingCriterion>>,
+    votes: HashMap<(i32, i64), bool>,
+    reactions: HashMap<(i32, i64), String>,
+    reply_roles: HashMap<i64, i64>,
+    api_keys: Vec<(ApiKey, String)>,
+    api_writes: Vec<(i32, String, Option<i32>)>,
+    next_id: i32,
+}
+
+// Backs the handlers and routes without Postgres or Redis, mainly for tests.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Inner {
+    // Reviews that are not soft-deleted, like `deleted_at IS NULL` in every Postgres read
+    fn live(&self) -> impl Iterator<Item = &Review> {
+        self.reviews.iter().filter(move |r| !self.deletions.contains_key(&r.id))
+    }
+
+    fn live_mut(&mut self, review_id: i32) -> Option<&mut Review> {
+        let deletions = &self.deletions;
+        self.reviews
+            .iter_mut()
+            .find(|r| r.id == review_id && !deletions.contains_key(&r.id))
+    }
+
+    fn soft_delete(&mut self, review_id: i32, deleted_by: i64, reason: Option<String>) {
+        self.deletions.insert(
+            review_id,
+            Deletion {
+                deleted_at: OffsetDateTime::now_utc(),
+                deleted_by,
+                reason,
+            },
+        );
+    }
+
+    fn matching<'a>(
+        &'a self,
+        target_id: i64,
+        review_type: &'a ReviewType,
+    ) -> impl Iterator<Item = &'a Review> {
+        self.live()
+            .filter(move |r| r.target_id == target_id && &r.review_type == review_type)
+    }
+
+    fn published<'a>(
+        &'a self,
+        target_id: i64,
+        review_type: &'a ReviewType,
+    ) -> impl Iterator<Item = &'a Review> {
+        self.matching(target_id, review_type)
+            .filter(move |r| self.is_published(r.id) && !self.is_hidden_everywhere(r.reviewer_id))
+    }
+
+    fn active_blacklist(&self, user_id: i64) -> impl Iterator<Item = &BlacklistEntry> {
+        let now = OffsetDateTime::now_utc();
+        self.blacklist
+            .iter()
+            .filter(move |e| e.user_id == user_id && e.expires_at.is_none_or(|at| at > now))
+    }
+
+    fn active_flag(&self, target_id: i64, review_type: &ReviewType) -> Option<&TargetFlag> {
+        let now = OffsetDateTime::now_utc();
+        self.flags
+            .iter()
+            .find(|(f, cleared_at)| {
+                f.target_id == target_id
+                    && &f.review_type == review_type
+                    && cleared_at.is_none()
+                    && f.expires_at.is_some_and(|at| at > now)
+            })
+            .map(|(f, _)| f)
+    }
+
+    fn is_hidden_everywhere(&self, user_id: i64) -> bool {
+        self.active_blacklist(user_id).any(|e| e.scope == BlacklistScope::Everywhere)
+    }
+
+    fn is_published(&self, review_id: i32) -> bool {
+        self.statuses.get(&review_id) == Some(&ReviewStatus::Published)
+    }
+}
+
+// The filters of GET /reviews, applied in Rust where Postgres has them in the WHERE clause
+fn query_matches(query: &ReviewQuery, review: &Review) -> bool {
+    query.min_rating.is_none_or(|min| review.rating >= min)
+        && query.max_rating.is_none_or(|max| review.rating <= max)
+        && query.has_comment.is_none_or(|wanted| {
+            review.comment.as_deref().is_some_and(|c| !c.is_empty()) == wanted
+        })
+        && query.since.is_none_or(|since| review.created_at.is_some_and(|at| at >= since))
+        && query.until.is_none_or(|until| review.created_at.is_some_and(|at| at < until))
+}
+
+impl MemoryStore {
+    // What Postgres keeps in deleted_at, deleted_by and delete_reason
+    pub fn deletion(&self, review_id: i32) -> Option<Deletion> {
+        self.inner.lock().unwrap().deletions.get(&review_id).cloned()
+    }
+
+    // Moves a review into the past, for checks on how ratings age
+    pub fn backdate(&self, review_id: i32, created_at: OffsetDateTime) {
+        if let Some(review) = self.inner.lock().unwrap().live_mut(review_id) {
+            review.created_at = Some(created_at);
+        }
+    }
+}
+
+impl ReviewStore for MemoryStore {
+    async fn upsert_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        rating: i32,
+        comment: Option<String>,
+        criteria: BTreeMap<String, i32>,
+        review_type: &ReviewType,
+        status: ReviewStatus,
+    ) -> Result<(Review, bool), sqlx::Error> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        if let Some(review) = inner
+            .reviews
+            .iter_mut()
+            .find(|r| r.target_id == target_id && r.reviewer_id == reviewer_id && &r.review_type == review_type)
+        {
+            // A soft-deleted row is revived in place and counts as a new review, as in Postgres
+            let revived = inner.deletions.remove(&review.id).is_some();
+            if review.rating != rating || review.comment != comment {
+                inner.revisions.push(ReviewRevision {
+                    id: inner.revisions.len() as i32 + 1,
+                    review_id: review.id,
+                    rating: review.rating,
+                    comment: review.comment.take(),
+                    written_at: review.updated_at.or(review.created_at),
+                    replaced_at: Some(OffsetDateTime::now_utc()),
+                });
+                review.rating = rating;
+                review.comment = comment;
+                review.updated_at = Some(OffsetDateTime::now_utc());
+            }
+            if review.criteria.0 != criteria {
+                review.criteria = Json(criteria);
+                review.updated_at = Some(OffsetDateTime::now_utc());
+            }
+            if revived {
+                review.created_at = Some(OffsetDateTime::now_utc());
+                review.updated_at = None;
+                review.reply = None;
+                review.reply_by = None;
+                review.replied_at = None;
+            }
+            if revived || inner.statuses.get(&review.id) != Some(&ReviewStatus::Hidden) {
+                inner.statuses.insert(review.id, status);
+            }
+            return Ok((review.clone(), revived));
+        }
+
+        inner.next_id += 1;
+        let review = Review {
+            id: inner.next_id,
+            target_id,
+            reviewer_id,
+            rating,
+            comment,
+            review_type: review_type.clone(),
+            created_at: Some(OffsetDateTime::now_utc()),
+            updated_at: None,
+            criteria: Json(criteria),
+            helpful_votes: 0,
+            unhelpful_votes: 0,
+            reply: None,
+            reply_by: None,
+            replied_at: None,
+        };
+        inner.reviews.push(review.clone());
+        inner.statuses.insert(review.id, status);
+
+        Ok((review, true))
+    }
+
+    async fn has_reviewed(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<bool, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        let found = inner.matching(target_id, review_type).any(|r| r.reviewer_id == reviewer_id);
+        Ok(found)
+    }
+
+    async fn get_paginated_reviews(
+        &self,
+        target_id: i64,
+        page: i64,
+        per_page: i64,
+        review_type: &ReviewType,
+        query: &ReviewQuery,
+    ) -> Result<Vec<Review>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut reviews: Vec<Review> = inner.published(target_id, review_type)
+            .filter(|r| query_matches(query, r))
+            .cloned()
+            .collect();
+        // Every sort falls back to newest first and then the id, like the ORDER BY in Postgres
+        reviews.sort_by(|a, b| {
+            let primary = match query.sort {
+                ReviewSort::Newest => Ordering::Equal,
+                ReviewSort::Oldest => a.created_at.cmp(&b.created_at),
+                ReviewSort::Highest => b.rating.cmp(&a.rating),
+                ReviewSort::Lowest => a.rating.cmp(&b.rating),
+                ReviewSort::Helpful => {
+                    (b.helpful_votes - b.unhelpful_votes).cmp(&(a.helpful_votes - a.unhelpful_votes))
+                }
+            };
+            primary
+                .then(b.created_at.cmp(&a.created_at))
+                .then(b.id.cmp(&a.id))
+        });
+
+        Ok(reviews
+            .into_iter()
+            .skip(page.saturating_mul(per_page).max(0) as usize)
+            .take(per_page.max(0) as usize)
+            .collect())
+    }
+
+    async fn count_matching_reviews(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        query: &ReviewQuery,
+    ) -> Result<i64, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.published(target_id, review_type).filter(|r| query_matches(query, r)).count() as i64)
+    }
+
+    async fn get_average_rating(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<Option<f64>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        if let Some(frozen) = inner.active_flag(target_id, review_type).and_then(|f| f.frozen_average) {
+            return Ok(Some(frozen));
+        }
+
+        let ratings: Vec<f64> = inner.published(target_id, review_type)
+            .map(|r| r.rating as f64)
+            .collect();
+
+        if ratings.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(ratings.iter().sum::<f64>() / ratings.len() as f64))
+    }
+
+    async fn get_reviews_count(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<i64, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.published(target_id, review_type).count() as i64)
+    }
+
+    async fn get_rating_distribution(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<RatingDistribution, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut counts = [0; 5];
+        for review in inner.published(target_id, review_type) {
+            if let Some(count) = counts.get_mut((review.rating - 1) as usize) {
+                *count += 1;
+            }
+        }
+
+        Ok(RatingDistribution::from_counts(counts))
+    }
+
+    async fn get_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<Option<Review>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        let review = inner
+            .matching(target_id, review_type)
+            .find(|r| r.reviewer_id == reviewer_id && !inner.is_hidden_everywhere(r.reviewer_id))
+            .cloned();
+        Ok(review)
+    }
+
+    async fn get_review_revisions(&self, review_id: i32) -> Result<Vec<ReviewRevision>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .revisions
+            .iter()
+            .rev()
+            .filter(|r| r.review_id == review_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+        deleted_by: i64,
+        reason: Option<String>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let review_id = match inner.matching(target_id, review_type).find(|r| r.reviewer_id == reviewer_id) {
+            Some(review) => review.id,
+            None => return Ok(false),
+        };
+        inner.soft_delete(review_id, deleted_by, reason);
+
+        Ok(true)
+    }
+
+    async fn get_review_by_id(&self, review_id: i32) -> Result<Option<Review>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        let review = inner.live().find(|r| r.id == review_id).cloned();
+        Ok(review)
+    }
+
+    async fn get_review_status(&self, review_id: i32) -> Result<Option<ReviewStatus>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        let status = inner
+            .live()
+            .find(|r| r.id == review_id)
+            .and_then(|r| inner.statuses.get(&r.id))
+            .cloned();
+        Ok(status)
+    }
+
+    async fn report_review(
+        &self,
+        review_id: i32,
+        reporter_id: i64,
+        reason: String,
+    ) -> Result<Option<ReviewReport>, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let reportable = inner.live().any(|r| r.id == review_id) && inner.is_published(review_id);
+        let duplicate = inner.reports.iter().any(|r| r.review_id == review_id && r.reporter_id == reporter_id);
+        if !reportable || duplicate {
+            return Ok(None);
+        }
+
+        let report = ReviewReport {
+            id: inner.reports.len() as i32 + 1,
+            review_id,
+            reporter_id,
+            reason,
+            created_at: Some(OffsetDateTime::now_utc()),
+            resolution: None,
+            resolved_by: None,
+            resolved_at: None,
+        };
+        inner.reports.push(report.clone());
+
+        Ok(Some(report))
+    }
+
+    async fn resolve_report(
+        &self,
+        report_id: i32,
+        moderator_id: i64,
+        resolution: ReportResolution,
+    ) -> Result<Option<ReviewReport>, sqlx::Error> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        let review_id = match inner.reports.iter().find(|r| r.id == report_id && r.resolution.is_none()) {
+            Some(report) => report.review_id,
+            None => return Ok(None),
+        };
+
+        match resolution {
+            ReportResolution::Dismissed => {}
+            ReportResolution::Hidden => {
+                inner.statuses.insert(review_id, ReviewStatus::Hidden);
+            }
+            ReportResolution::Deleted => {
+                inner.soft_delete(review_id, moderator_id, Some("Removed after report".to_string()))
+            }
+        }
+
+        let settles_all = resolution != ReportResolution::Dismissed;
+        for report in inner.reports.iter_mut() {
+            let affected = report.id == report_id || (settles_all && report.review_id == review_id);
+            if affected && report.resolution.is_none() {
+                report.resolution = Some(resolution.clone());
+                report.resolved_by = Some(moderator_id);
+                report.resolved_at = Some(OffsetDateTime::now_utc());
+            }
+        }
+
+        Ok(inner.reports.iter().find(|r| r.id == report_id).cloned())
+    }
+
+    async fn resolve_pending_review(
+        &self,
+        review_id: i32,
+        moderator_id: i64,
+        approve: bool,
+    ) -> Result<Option<Review>, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let review = match inner.live().find(|r| r.id == review_id) {
+            Some(review) if inner.statuses.get(&review_id) == Some(&ReviewStatus::Pending) => review.clone(),
+            _ => return Ok(None),
+        };
+        if approve {
+            inner.statuses.insert(review_id, ReviewStatus::Published);
+        } else {
+            inner.soft_delete(review_id, moderator_id, Some("Rejected by moderation".to_string()));
+        }
+
+        Ok(Some(review))
+    }
+
+    async fn blacklist_user(
+        &self,
+        user_id: i64,
+        scope: BlacklistScope,
+        reason: Option<String>,
+        expires_at: Option<OffsetDateTime>,
+        created_by: i64,
+    ) -> Result<BlacklistEntry, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.blacklist.retain(|e| !(e.user_id == user_id && e.scope == scope));
+
+        let entry = BlacklistEntry {
+            id: inner.blacklist.iter().map(|e| e.id).max().unwrap_or(0) + 1,
+            user_id,
+            scope,
+            reason,
+            created_by,
+            created_at: Some(OffsetDateTime::now_utc()),
+            expires_at,
+        };
+        inner.blacklist.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn unblacklist_user(&self, user_id: i64, scope: BlacklistScope) -> Result<bool, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.blacklist.len();
+
+        inner.blacklist.retain(|e| !(e.user_id == user_id && e.scope == scope));
+
+        Ok(inner.blacklist.len() != before)
+    }
+
+    async fn purge_expired_blacklist(&self) -> Result<u64, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.blacklist.len();
+        let now = OffsetDateTime::now_utc();
+
+        inner.blacklist.retain(|e| e.expires_at.is_none_or(|expires_at| expires_at > now));
+
+        Ok((before - inner.blacklist.len()) as u64)
+    }
+
+    async fn get_active_blacklist(&self, user_id: i64) -> Result<Vec<BlacklistEntry>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.active_blacklist(user_id).cloned().collect())
+    }
+
+    async fn get_review_activity(
+        &self,
+        since: OffsetDateTime,
+        min_reviews: i64,
+    ) -> Result<Vec<TargetActivity>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut activity: Vec<TargetActivity> = Vec::new();
+        let mut baselines: HashMap<(i64, &str), (f64, i64)> = HashMap::new();
+
+        for review in inner
+            .live()
+            .filter(|r| inner.is_published(r.id) && !inner.is_hidden_everywhere(r.reviewer_id))
+        {
+            if review.created_at.is_some_and(|at| at >= since) {
+                match activity
+                    .iter_mut()
+                    .find(|a| a.target_id == review.target_id && a.review_type == review.review_type)
+                {
+                    Some(a) => {
+                        a.recent_average += review.rating as f64;
+                        a.recent_reviews += 1;
+                        a.recent_reviewers.push(review.reviewer_id);
+                    }
+                    None => activity.push(TargetActivity {
+                        target_id: review.target_id,
+                        review_type: review.review_type.clone(),
+                        recent_reviews: 1,
+                        recent_average: review.rating as f64,
+                        baseline_average: None,
+                        recent_reviewers: vec![review.reviewer_id],
+                    }),
+                }
+            } else {
+                let entry = baselines
+                    .entry((review.target_id, review.review_type.as_str()))
+                    .or_insert((0.0, 0));
+                entry.0 += review.rating as f64;
+                entry.1 += 1;
+            }
+        }
+
+        activity.retain(|a| a.recent_reviews >= min_reviews);
+        for a in activity.iter_mut() {
+            a.recent_average /= a.recent_reviews as f64;
+            a.baseline_average = baselines
+                .get(&(a.target_id, a.review_type.as_str()))
+                .map(|(sum, count)| sum / *count as f64);
+        }
+
+        Ok(activity)
+    }
+
+    async fn flag_target(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        reason: String,
+        frozen_average: Option<f64>,
+        since: OffsetDateTime,
+        expires_at: OffsetDateTime,
+    ) -> Result<Option<TargetFlag>, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        let existing = inner
+            .flags
+            .iter()
+            .position(|(f, _)| f.target_id == target_id && &f.review_type == review_type);
+
+        if let Some(index) = existing {
+            let (flag, cleared_at) = &inner.flags[index];
+            let replaceable = match cleared_at {
+                Some(cleared_at) => *cleared_at < since,
+                None => flag.expires_at.is_none_or(|at| at <= now),
+            };
+            if !replaceable {
+                return Ok(None);
+            }
+            inner.flags.remove(index);
+        }
+
+        let flag = TargetFlag {
+            id: inner.flags.iter().map(|(f, _)| f.id).max().unwrap_or(0) + 1,
+            target_id,
+            review_type: review_type.clone(),
+            reason,
+            frozen_average,
+            flagged_at: Some(now),
+            expires_at: Some(expires_at),
+        };
+        inner.flags.push((flag.clone(), None));
+
+        Ok(Some(flag))
+    }
+
+    async fn get_target_flag(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> Result<Option<TargetFlag>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.active_flag(target_id, review_type).cloned())
+    }
+
+    async fn clear_target_flag(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        _moderator_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        let id = match inner.active_flag(target_id, review_type) {
+            Some(flag) => flag.id,
+            None => return Ok(false),
+        };
+        if let Some((_, cleared_at)) = inner.flags.iter_mut().find(|(f, _)| f.id == id) {
+            *cleared_at = Some(OffsetDateTime::now_utc());
+        }
+
+        Ok(true)
+    }
+
+    async fn get_global_average(&self, review_type: &ReviewType) -> Result<Option<f64>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let ratings: Vec<f64> = inner
+            .live()
+            .filter(|r| &r.review_type == review_type)
+            .filter(|r| inner.is_published(r.id) && !inner.is_hidden_everywhere(r.reviewer_id))
+            .map(|r| r.rating as f64)
+            .collect();
+
+        if ratings.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(ratings.iter().sum::<f64>() / ratings.len() as f64))
+    }
+
+    async fn get_rated_targets(&self, review_type: &ReviewType) -> Result<Vec<RatedTarget>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut totals: HashMap<i64, (i64, i64)> = HashMap::new();
+        for review in inner.live().filter(|r| &r.review_type == review_type) {
+            if !inner.is_published(review.id) || inner.is_hidden_everywhere(review.reviewer_id) {
+                continue;
+            }
+            let entry = totals.entry(review.target_id).or_default();
+            entry.0 += 1;
+            entry.1 += review.rating as i64;
+        }
+
+        Ok(totals
+            .into_iter()
+            .filter(|(target_id, _)| inner.active_flag(*target_id, review_type).is_none())
+            .map(|(target_id, (review_count, rating_sum))| RatedTarget {
+                target_id,
+                review_count,
+                average: rating_sum as f64 / review_count as f64,
+            })
+            .collect())
+    }
+
+    async fn get_weighted_rating(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        since: Option<OffsetDateTime>,
+        half_life: Option<Duration>,
+    ) -> Result<WeightedRating, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        let mut total = 0.0;
+        let mut weight = 0.0;
+        for review in inner.published(target_id, review_type) {
+            let created_at = review.created_at.unwrap_or(now);
+            if since.is_some_and(|since| created_at < since) {
+                continue;
+            }
+            let w = half_life.map_or(1.0, |h| 0.5f64.powf((now - created_at).as_seconds_f64() / h.as_seconds_f64()));
+            total += review.rating as f64 * w;
+            weight += w;
+        }
+
+        let frozen = inner.active_flag(target_id, review_type).and_then(|f| f.frozen_average);
+        let average = frozen.or((weight > 0.0).then(|| total / weight));
+
+        Ok(WeightedRating { average, weight })
+    }
+
+    async fn create_api_key(
+        &self,
+        name: String,
+        key_prefix: String,
+        key_hash: String,
+        scopes: Vec<String>,
+        created_by: i64,
+    ) -> Result<ApiKey, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let key = ApiKey {
+            id: inner.api_keys.len() as i32 + 1,
+            name,
+            key_prefix,
+            scopes,
+            created_by,
+            created_at: Some(OffsetDateTime::now_utc()),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        inner.api_keys.push((key.clone(), key_hash));
+        Ok(key)
+    }
+
+    async fn revoke_api_key(&self, key_id: i32) -> Result<bool, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.api_keys.iter_mut().find(|(k, _)| k.id == key_id && k.revoked_at.is_none()) {
+            Some((key, _)) => {
+                key.revoked_at = Some(OffsetDateTime::now_utc());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.api_keys.iter().map(|(k, _)| k.clone()).collect())
+    }
+
+    async fn authenticate_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let found = inner
+            .api_keys
+            .iter_mut()
+            .find(|(k, hash)| hash == key_hash && k.revoked_at.is_none());
+
+        Ok(found.map(|(key, _)| {
+            key.last_used_at = Some(OffsetDateTime::now_utc());
+            key.clone()
+        }))
+    }
+
+    async fn record_api_write(
+        &self,
+        api_key_id: i32,
+        action: &str,
+        review_id: Option<i32>,
+        _target_id: i64,
+        _reviewer_id: i64,
+        _review_type: &ReviewType,
+    ) -> Result<(), sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.api_writes.push((api_key_id, action.to_string(), review_id));
+        Ok(())
+    }
+
+    async fn get_score_view(&self, guild_id: i64) -> Result<Option<ScoreView>, sqlx::Error> {
+        Ok(self.inner.lock().unwrap().score_views.get(&guild_id).copied())
+    }
+
+    async fn set_score_view(&self, guild_id: i64, view: ScoreView, _updated_by: i64) -> Result<(), sqlx::Error> {
+        self.inner.lock().unwrap().score_views.insert(guild_id, view);
+        Ok(())
+    }
+
+    async fn get_criteria(
+        &self,
+        review_type: &ReviewType,
+        guild_id: Option<i64>,
+    ) -> Result<Vec<RatingCriterion>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        if let Some(criteria) = guild_id.and_then(|id| inner.guild_criteria.get(&(review_type.as_str(), id))) {
+            return Ok(criteria.clone());
+        }
+
+        // Same defaults the migration seeds
+        let labels: &[&str] = match review_type {
+            ReviewType::Server => &["Moderation", "Activity", "Friendliness"],
+            ReviewType::User => &["Trustworthiness", "Communication"],
+        };
+        Ok(labels.iter().map(|label| RatingCriterion::from_label(label)).collect())
+    }
+
+    async fn set_guild_criteria(
+        &self,
+        review_type: &ReviewType,
+        guild_id: i64,
+        criteria: Vec<RatingCriterion>,
+    ) -> Result<(), sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let key = (review_type.as_str(), guild_id);
+
+        if criteria.is_empty() {
+            inner.guild_criteria.remove(&key);
+        } else {
+            inner.guild_criteria.insert(key, criteria);
+        }
+
+        Ok(())
+    }
+
+    async fn get_criteria_averages(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        keys: &[String],
+    ) -> Result<BTreeMap<String, f64>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut totals: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+        for review in inner.published(target_id, review_type) {
+            for (key, rating) in review.criteria.iter().filter(|(key, _)| keys.contains(key)) {
+                let entry = totals.entry(key.clone()).or_default();
+                entry.0 += 1;
+                entry.1 += *rating as i64;
+            }
+        }
+
+        Ok(totals
+            .into_iter()
+            .map(|(key, (count, sum))| (key, sum as f64 / count as f64))
+            .collect())
+    }
+
+    async fn vote_review(
+        &self,
+        review_id: i32,
+        voter_id: i64,
+        helpful: bool,
+    ) -> Result<Option<VoteTally>, sqlx::Error> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        let deletions = &inner.deletions;
+        let review = match inner.reviews.iter_mut().find(|r| r.id == review_id && !deletions.contains_key(&r.id)) {
+            Some(review) => review,
+            None => return Ok(None),
+        };
+
+        let vote = if inner.votes.get(&(review_id, voter_id)) == Some(&helpful) {
+            inner.votes.remove(&(review_id, voter_id));
+            None
+        } else {
+            inner.votes.insert((review_id, voter_id), helpful);
+            Some(helpful)
+        };
+
+        let votes = inner.votes.iter().filter(|((id, _), _)| *id == review_id);
+        review.helpful_votes = votes.clone().filter(|(_, helpful)| **helpful).count() as i32;
+        review.unhelpful_votes = votes.filter(|(_, helpful)| !**helpful).count() as i32;
+
+        Ok(Some(VoteTally {
+            helpful_votes: review.helpful_votes,
+            unhelpful_votes: review.unhelpful_votes,
+            vote,
+        }))
+    }
+
+    async fn react_to_review(&self, review_id: i32, user_id: i64, emoji: String) -> Result<bool, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if !inner.live().any(|r| r.id == review_id) {
+            return Ok(false);
+        }
+        inner.reactions.insert((review_id, user_id), emoji);
+
+        Ok(true)
+    }
+
+    async fn get_review_reactions(&self, review_id: i32) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut counts: HashMap<&str, i64> = HashMap::new();
+        for ((id, _), emoji) in &inner.reactions {
+            if *id == review_id {
+                *counts.entry(emoji.as_str()).or_default() += 1;
+            }
+        }
+
+        let mut reactions: Vec<(String, i64)> = counts
+            .into_iter()
+            .map(|(emoji, count)| (emoji.to_string(), count))
+            .collect();
+        reactions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        Ok(reactions)
+    }
+
+    async fn reply_to_review(
+        &self,
+        review_id: i32,
+        author_id: i64,
+        reply: Option<String>,
+    ) -> Result<Option<Review>, sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let review = match inner.live_mut(review_id) {
+            Some(review) => review,
+            None => return Ok(None),
+        };
+        review.reply_by = reply.as_ref().map(|_| author_id);
+        review.replied_at = reply.as_ref().map(|_| OffsetDateTime::now_utc());
+        review.reply = reply;
+
+        Ok(Some(review.clone()))
+    }
+
+    async fn get_reply_role(&self, guild_id: i64) -> Result<Option<i64>, sqlx::Error> {
+        Ok(self.inner.lock().unwrap().reply_roles.get(&guild_id).copied())
+    }
+
+    async fn set_reply_role(&self, guild_id: i64, role_id: Option<i64>, _updated_by: i64) -> Result<(), sqlx::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        match role_id {
+            Some(role_id) => inner.reply_roles.insert(guild_id, role_id),
+            None => inner.reply_roles.remove(&guild_id),
+        };
+        Ok(())
+    }
+}
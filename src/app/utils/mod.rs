@@ -1,4 +1,18 @@
-pub mod cache;
-pub mod database;
-pub mod colors;
-pub mod datetime; 
\ No newline at end of file
+pub mod cache;
+pub mod redis_cache;
+pub mod memory_cache;
+pub mod database;
+pub mod colors;
+pub mod datetime;
+pub mod store;
+#[cfg(test)]
+pub mod memory_store;
+pub mod permissions;
+pub mod moderation;
+pub mod blacklist;
+pub mod rate_limit;
+pub mod bombing;
+pub mod single_flight;
+pub mod scoring;
+pub mod api_keys;
+pub mod validation;
@@ -0,0 +1,294 @@
+use std::env;
+use crate::app::utils::validation;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Hold(String),
+    Reject(String),
+}
+
+pub trait ModerationCheck: Send + Sync {
+    fn check(&self, comment: &str) -> Verdict;
+}
+
+pub struct WordListCheck {
+    rejected: Vec<String>,
+    held: Vec<String>,
+}
+
+impl WordListCheck {
+    pub fn new(rejected: Vec<String>, held: Vec<String>) -> Self {
+        let lower = |words: Vec<String>| words.into_iter().map(|w| w.to_lowercase()).collect();
+        Self { rejected: lower(rejected), held: lower(held) }
+    }
+}
+
+impl ModerationCheck for WordListCheck {
+    fn check(&self, comment: &str) -> Verdict {
+        let comment = comment.to_lowercase();
+        let words: Vec<&str> = comment
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        if self.rejected.iter().any(|w| words.contains(&w.as_str())) {
+            return Verdict::Reject("contains a blocked word".to_string());
+        }
+        if self.held.iter().any(|w| words.contains(&w.as_str())) {
+            return Verdict::Hold("contains a flagged word".to_string());
+        }
+        Verdict::Allow
+    }
+}
+
+pub struct LinkCheck {
+    allow_links: bool,
+}
+
+impl LinkCheck {
+    pub fn new(allow_links: bool) -> Self {
+        Self { allow_links }
+    }
+}
+
+// Undoes the usual ways of slipping a link past the filter: spaces, and dots spelled out or bracketed
+fn squash(comment: &str) -> String {
+    let spaced = comment.to_lowercase().replace(" dot ", ".");
+    let mut squashed: String = spaced.chars().filter(|c| !c.is_whitespace()).collect();
+    for dot in ["(dot)", "[dot]", "{dot}", "(.)", "[.]", "{.}"] {
+        squashed = squashed.replace(dot, ".");
+    }
+    squashed
+}
+
+impl ModerationCheck for LinkCheck {
+    fn check(&self, comment: &str) -> Verdict {
+        let comment = squash(comment);
+
+        let invites = ["discord.gg", "discord.com/invite", "discordapp.com/invite"];
+        if invites.iter().any(|i| comment.contains(i)) {
+            return Verdict::Reject("contains a server invite".to_string());
+        }
+
+        let links = ["http://", "https://", "www."];
+        if !self.allow_links && links.iter().any(|l| comment.contains(l)) {
+            return Verdict::Hold("contains a link".to_string());
+        }
+        Verdict::Allow
+    }
+}
+
+pub struct MaxLengthCheck {
+    max_length: usize,
+}
+
+impl MaxLengthCheck {
+    pub fn new(max_length: usize) -> Self {
+        Self { max_length }
+    }
+}
+
+impl ModerationCheck for MaxLengthCheck {
+    fn check(&self, comment: &str) -> Verdict {
+        if comment.chars().count() > self.max_length {
+            return Verdict::Reject(format!("is longer than {} characters", self.max_length));
+        }
+        Verdict::Allow
+    }
+}
+
+pub struct SpamCheck {
+    max_repeated: usize,
+}
+
+impl SpamCheck {
+    pub fn new(max_repeated: usize) -> Self {
+        Self { max_repeated }
+    }
+}
+
+impl ModerationCheck for SpamCheck {
+    fn check(&self, comment: &str) -> Verdict {
+        let mut run = 0;
+        let mut previous = None;
+        for c in comment.chars().filter(|c| !c.is_whitespace()) {
+            run = if previous == Some(c) { run + 1 } else { 1 };
+            previous = Some(c);
+            if run > self.max_repeated {
+                return Verdict::Hold("repeats the same character too many times".to_string());
+            }
+        }
+
+        let letters: Vec<char> = comment.chars().filter(|c| c.is_alphabetic()).collect();
+        let upper = letters.iter().filter(|c| c.is_uppercase()).count();
+        if letters.len() >= 12 && upper * 10 >= letters.len() * 8 {
+            return Verdict::Hold("is written mostly in capital letters".to_string());
+        }
+        Verdict::Allow
+    }
+}
+
+pub struct ModerationPipeline {
+    checks: Vec<Box<dyn ModerationCheck>>,
+}
+
+impl ModerationPipeline {
+    pub fn new(checks: Vec<Box<dyn ModerationCheck>>) -> Self {
+        Self { checks }
+    }
+
+    pub fn from_env() -> Self {
+        let list = |name: &str| -> Vec<String> {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|w| w.trim().to_string())
+                .filter(|w| !w.is_empty())
+                .collect()
+        };
+        let allow_links = env::var("MODERATION_ALLOW_LINKS").is_ok_and(|v| v == "true");
+
+        // Same limit validation enforces on reviews, so it only ever catches replies
+        Self::new(vec![
+            Box::new(MaxLengthCheck::new(validation::MAX_COMMENT_LENGTH)),
+            Box::new(WordListCheck::new(list("MODERATION_BLOCKED_WORDS"), list("MODERATION_FLAGGED_WORDS"))),
+            Box::new(LinkCheck::new(allow_links)),
+            Box::new(SpamCheck::new(5)),
+        ])
+    }
+
+    /// Runs every check; any rejection wins, otherwise all hold reasons are reported together.
+    pub fn review(&self, comment: Option<&str>) -> Verdict {
+        let comment = match comment {
+            Some(comment) => comment,
+            None => return Verdict::Allow,
+        };
+
+        let mut held = Vec::new();
+        for check in &self.checks {
+            match check.check(comment) {
+                Verdict::Allow => {}
+                Verdict::Hold(reason) => held.push(reason),
+                Verdict::Reject(reason) => return Verdict::Reject(reason),
+            }
+        }
+
+        if held.is_empty() {
+            Verdict::Allow
+        } else {
+            Verdict::Hold(held.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(rejected: &[&str], held: &[&str]) -> WordListCheck {
+        let owned = |words: &[&str]| words.iter().map(|w| w.to_string()).collect();
+        WordListCheck::new(owned(rejected), owned(held))
+    }
+
+    fn is_hold(verdict: Verdict) -> bool {
+        matches!(verdict, Verdict::Hold(_))
+    }
+
+    fn is_reject(verdict: Verdict) -> bool {
+        matches!(verdict, Verdict::Reject(_))
+    }
+
+    #[test]
+    fn matches_listed_words_whole_and_in_any_case() {
+        let check = words(&["Scam"], &["refund"]);
+
+        assert!(is_reject(check.check("Total SCAM, avoid")));
+        assert!(is_reject(check.check("scam!")));
+        assert!(is_hold(check.check("Asked for a refund")));
+        assert_eq!(check.check("Called out the scammer, got my refunds"), Verdict::Allow);
+        // A blocked word outranks a flagged one
+        assert!(is_reject(check.check("refund scam")));
+    }
+
+    #[test]
+    fn rejects_invites_even_when_links_are_allowed() {
+        for comment in [
+            "join discord.gg/abc",
+            "JOIN DISCORD.GG/ABC",
+            "https://discord.com/invite/abc",
+            "discordapp.com/invite/abc",
+            "discord . gg / abc",
+            "discord dot gg/abc",
+            "discord(dot)gg/abc",
+            "discord [.] gg/abc",
+        ] {
+            assert!(is_reject(LinkCheck::new(false).check(comment)), "{}", comment);
+            assert!(is_reject(LinkCheck::new(true).check(comment)), "{}", comment);
+        }
+    }
+
+    #[test]
+    fn holds_links_unless_allowed() {
+        for comment in [
+            "see https://example.com",
+            "http://example.com",
+            "www.example.com",
+            "www (dot) example.com",
+            "h t t p s : / / example.com",
+        ] {
+            assert!(is_hold(LinkCheck::new(false).check(comment)), "{}", comment);
+            assert_eq!(LinkCheck::new(true).check(comment), Verdict::Allow, "{}", comment);
+        }
+        assert_eq!(LinkCheck::new(false).check("Fast trade, e.g. within minutes. Would dot the i's again"), Verdict::Allow);
+    }
+
+    #[test]
+    fn holds_long_runs_of_one_character() {
+        let check = SpamCheck::new(5);
+
+        assert_eq!(check.check("Sooooo good"), Verdict::Allow);
+        assert!(is_hold(check.check("Soooooo good")));
+        // Spaces do not break a run
+        assert!(is_hold(check.check("a a a a a a")));
+    }
+
+    #[test]
+    fn holds_comments_mostly_in_capitals() {
+        let check = SpamCheck::new(5);
+
+        assert!(is_hold(check.check("GREAT TRADER THANKS")));
+        assert_eq!(check.check("GREAT TRADER thanks"), Verdict::Allow);
+        // Ten of twelve letters is just enough
+        assert!(is_hold(check.check("GREAT TRADEer")));
+        assert_eq!(check.check("GREAT TRADeer"), Verdict::Allow);
+        // Short shouting is left alone
+        assert_eq!(check.check("GREAT TRADE"), Verdict::Allow);
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        let check = MaxLengthCheck::new(5);
+
+        assert_eq!(check.check("ééééé"), Verdict::Allow);
+        assert!(is_reject(check.check("éééééé")));
+    }
+
+    #[test]
+    fn pipeline_rejects_on_any_rejection_and_gathers_holds() {
+        let pipeline = ModerationPipeline::new(vec![
+            Box::new(LinkCheck::new(false)),
+            Box::new(SpamCheck::new(5)),
+            Box::new(WordListCheck::new(vec!["scam".to_string()], Vec::new())),
+        ]);
+
+        assert_eq!(pipeline.review(None), Verdict::Allow);
+        assert_eq!(pipeline.review(Some("Smooth trade")), Verdict::Allow);
+        assert_eq!(
+            pipeline.review(Some("www.example.com is goooooood")),
+            Verdict::Hold("contains a link, repeats the same character too many times".to_string())
+        );
+        // Rejected even though earlier checks only held it
+        assert!(is_reject(pipeline.review(Some("www.example.com is a scaaaaaaam scam"))));
+    }
+}
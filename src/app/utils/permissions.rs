@@ -0,0 +1,66 @@
+use poise::serenity_prelude::{Context, GuildId, Member, RoleId};
+use crate::app::{
+    models::review::{Review, ReviewType},
+    utils::store::ReviewStore,
+};
+
+pub fn moderator_role() -> Option<RoleId> {
+    std::env::var("MODERATOR_ROLE")
+        .ok()
+        .and_then(|role| role.parse::<u64>().ok())
+        .map(RoleId)
+}
+
+pub fn moderator_guild() -> Option<GuildId> {
+    std::env::var("MODERATOR_GUILD")
+        .ok()
+        .and_then(|guild| guild.parse::<u64>().ok())
+        .map(GuildId)
+}
+
+// Moderators hold MODERATOR_ROLE in the bot's own server. Permissions in any other server
+// do not count, since anyone can create a server they manage
+pub fn is_home_moderator(guild_id: Option<GuildId>, member: Option<&Member>) -> bool {
+    let (home, role) = match (moderator_guild(), moderator_role()) {
+        (Some(home), Some(role)) => (home, role),
+        _ => return false,
+    };
+
+    guild_id == Some(home) && member.is_some_and(|m| m.guild_id == home && m.roles.contains(&role))
+}
+
+pub fn can_delete_review(guild_id: Option<GuildId>, member: Option<&Member>, user_id: i64, reviewer_id: i64) -> bool {
+    user_id == reviewer_id || is_home_moderator(guild_id, member)
+}
+
+// The reviewed user answers their own reviews. A server's reviews are answered by its owner,
+// or from inside that server by members holding the role picked with /settings reply_role
+pub async fn can_reply<S: ReviewStore>(
+    ctx: &Context,
+    db: &S,
+    guild_id: Option<GuildId>,
+    member: Option<&Member>,
+    user_id: i64,
+    review: &Review,
+) -> Result<bool, sqlx::Error> {
+    if review.review_type == ReviewType::User {
+        return Ok(review.target_id == user_id);
+    }
+
+    let target_guild = GuildId(review.target_id as u64);
+    let owner_id = match ctx.cache.guild_field(target_guild, |g| g.owner_id) {
+        Some(owner_id) => Some(owner_id),
+        None => ctx.http.get_guild(target_guild.0).await.ok().map(|g| g.owner_id),
+    };
+    if owner_id.is_some_and(|owner| owner.0 as i64 == user_id) {
+        return Ok(true);
+    }
+
+    let member = match member {
+        Some(member) if guild_id == Some(target_guild) => member,
+        _ => return Ok(false),
+    };
+    let role = db.get_reply_role(review.target_id).await?;
+
+    Ok(role.is_some_and(|role| member.roles.contains(&RoleId(role as u64))))
+}
@@ -0,0 +1,205 @@
+use std::env;
+use tracing::error;
+use crate::app::{models::review::ReviewType, utils::cache::Cache};
+
+const WINDOW: usize = 3600;
+
+#[derive(Debug, Clone)]
+pub struct RateLimitRules {
+    pub reviews_per_hour: i64,
+    pub edit_gap: usize,
+    pub target_per_hour: i64,
+}
+
+impl RateLimitRules {
+    // A value of 0 turns the rule off
+    pub fn from_env() -> Self {
+        let number = |name: &str, default: i64| -> i64 {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            reviews_per_hour: number("RATE_LIMIT_REVIEWS_PER_HOUR", 5),
+            edit_gap: number("RATE_LIMIT_EDIT_GAP", 60).max(0) as usize,
+            target_per_hour: number("RATE_LIMIT_TARGET_PER_HOUR", 30),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    cache: Cache,
+    rules: RateLimitRules,
+}
+
+impl RateLimiter {
+    pub fn new(cache: Cache, rules: RateLimitRules) -> Self {
+        Self { cache, rules }
+    }
+
+    pub fn from_env(cache: Cache) -> Self {
+        Self::new(cache, RateLimitRules::from_env())
+    }
+
+    /// Takes a slot for the write about to happen, returns the seconds to wait instead when
+    /// there is none. Counters are bumped and read back in one step, so concurrent submissions
+    /// cannot all see room under a limit. Redis being unavailable never blocks a review.
+    pub async fn acquire(
+        &self,
+        reviewer_id: i64,
+        target_id: i64,
+        review_type: &ReviewType,
+        editing: bool,
+    ) -> Option<i64> {
+        let edit_key = edit_key(reviewer_id, target_id, review_type);
+
+        if editing {
+            if self.rules.edit_gap == 0 {
+                return None;
+            }
+            return match self.cache.claim_cooldown(&edit_key, self.rules.edit_gap).await {
+                Ok(remaining) => remaining,
+                Err(e) => {
+                    error!("Rate limit check failed: {}", e);
+                    None
+                }
+            };
+        }
+
+        let windows = [
+            (reviewer_key(reviewer_id), self.rules.reviews_per_hour),
+            (target_key(target_id, review_type), self.rules.target_per_hour),
+        ];
+
+        // A refused review gives back every slot it took, so it counts against neither window
+        let mut taken = Vec::new();
+        for (key, limit) in windows {
+            if limit <= 0 {
+                continue;
+            }
+            match self.cache.hit_window(&key, WINDOW).await {
+                Ok((count, ttl)) => {
+                    taken.push(key);
+                    if count > limit {
+                        self.release(&taken).await;
+                        return Some(if ttl > 0 { ttl } else { WINDOW as i64 });
+                    }
+                }
+                Err(e) => error!("Rate limit check failed: {}", e),
+            }
+        }
+
+        if self.rules.edit_gap > 0 {
+            if let Err(e) = self.cache.set_cooldown(&edit_key, self.rules.edit_gap).await {
+                error!("Failed to record edit cooldown: {}", e);
+            }
+        }
+        None
+    }
+
+    async fn release(&self, keys: &[String]) {
+        for key in keys {
+            if let Err(e) = self.cache.release_window(key).await {
+                error!("Failed to release rate limit slot: {}", e);
+            }
+        }
+    }
+}
+
+fn reviewer_key(reviewer_id: i64) -> String {
+    format!("ratelimit:reviewer:{}", reviewer_id)
+}
+
+fn target_key(target_id: i64, review_type: &ReviewType) -> String {
+    format!("ratelimit:{}:{}", review_type.as_str(), target_id)
+}
+
+fn edit_key(reviewer_id: i64, target_id: i64, review_type: &ReviewType) -> String {
+    format!("ratelimit:edit:{}:{}:{}", review_type.as_str(), target_id, reviewer_id)
+}
+
+pub fn format_wait(seconds: i64) -> String {
+    let plural = |n: i64, unit: &str| if n == 1 { format!("1 {}", unit) } else { format!("{} {}s", n, unit) };
+
+    match seconds {
+        s if s >= 3600 => plural((s + 3599) / 3600, "hour"),
+        s if s >= 60 => plural((s + 59) / 60, "minute"),
+        s => plural(s.max(1), "second"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::app::utils::memory_cache::MemoryCache;
+
+    fn limiter(reviews_per_hour: i64, edit_gap: usize) -> RateLimiter {
+        limiter_with_target(reviews_per_hour, edit_gap, 0)
+    }
+
+    fn limiter_with_target(reviews_per_hour: i64, edit_gap: usize, target_per_hour: i64) -> RateLimiter {
+        let rules = RateLimitRules {
+            reviews_per_hour,
+            edit_gap,
+            target_per_hour,
+        };
+        RateLimiter::new(Arc::new(MemoryCache::new(100)), rules)
+    }
+
+    async fn concurrently(limiter: &RateLimiter, editing: bool, targets: impl Iterator<Item = i64>) -> usize {
+        let attempts: Vec<_> = targets
+            .map(|target_id| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire(1, target_id, &ReviewType::User, editing).await })
+            })
+            .collect();
+
+        let mut allowed = 0;
+        for attempt in attempts {
+            if attempt.await.unwrap().is_none() {
+                allowed += 1;
+            }
+        }
+        allowed
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_reviews_cannot_overrun_the_window() {
+        let limiter = limiter(2, 0);
+
+        assert_eq!(concurrently(&limiter, false, 0..10).await, 2);
+        let wait = limiter.acquire(1, 99, &ReviewType::User, false).await.unwrap();
+        assert!(wait > 0 && wait <= WINDOW as i64);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_edits_share_one_cooldown() {
+        let limiter = limiter(0, 60);
+
+        assert_eq!(concurrently(&limiter, true, [5; 10].into_iter()).await, 1);
+    }
+
+    #[tokio::test]
+    async fn a_new_review_starts_the_edit_cooldown() {
+        let limiter = limiter(0, 60);
+
+        assert_eq!(limiter.acquire(1, 5, &ReviewType::User, false).await, None);
+        assert!(limiter.acquire(1, 5, &ReviewType::User, true).await.is_some());
+        assert_eq!(limiter.acquire(1, 6, &ReviewType::User, true).await, None);
+    }
+
+    #[tokio::test]
+    async fn a_busy_target_does_not_use_up_the_reviewer() {
+        let limiter = limiter_with_target(2, 0, 1);
+
+        assert_eq!(limiter.acquire(1, 5, &ReviewType::User, false).await, None);
+        assert!(limiter.acquire(2, 5, &ReviewType::User, false).await.is_some());
+        assert_eq!(limiter.acquire(2, 6, &ReviewType::User, false).await, None);
+        assert_eq!(limiter.acquire(2, 7, &ReviewType::User, false).await, None);
+        assert!(limiter.acquire(2, 8, &ReviewType::User, false).await.is_some());
+    }
+}
@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use lru::LruCache;
+use redis::{aio::ConnectionManager, AsyncCommands, Client, ErrorKind, RedisError, RedisResult};
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+use crate::app::utils::cache::{CacheBackend, Cached, CACHE_DURATION};
+
+const DEFAULT_TIMEOUT_MS: u64 = 500;
+const DEFAULT_LOCAL_CAPACITY: usize = 1000;
+const BREAKER_THRESHOLD: u32 = 5;
+const BREAKER_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+#[derive(Clone)]
+pub struct RedisCache {
+    client: Client,
+    conn: Arc<tokio::sync::Mutex<Option<ConnectionManager>>>,
+    timeout: Duration,
+    breaker: Arc<Mutex<Breaker>>,
+    // Keeps hot values around while Redis is unreachable
+    local: Arc<Mutex<LruCache<String, (String, Instant)>>>,
+}
+
+impl RedisCache {
+    // Connecting is deferred to the first call, so the bot also starts while Redis is down
+    pub async fn new(redis_url: &str) -> Result<Self, redis::RedisError> {
+        let client = Client::open(redis_url)?;
+        let env_number = |name: &str, default: u64| -> u64 {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let timeout = Duration::from_millis(env_number("REDIS_TIMEOUT_MS", DEFAULT_TIMEOUT_MS));
+        let capacity = NonZeroUsize::new(env_number("CACHE_LOCAL_CAPACITY", DEFAULT_LOCAL_CAPACITY as u64) as usize)
+            .unwrap_or(NonZeroUsize::MIN);
+
+        Ok(Self {
+            client,
+            conn: Arc::new(tokio::sync::Mutex::new(None)),
+            timeout,
+            breaker: Arc::new(Mutex::new(Breaker::default())),
+            local: Arc::new(Mutex::new(LruCache::new(capacity))),
+        })
+    }
+
+    async fn connection(&self) -> RedisResult<ConnectionManager> {
+        let mut conn = self.conn.lock().await;
+        if let Some(conn) = conn.as_ref() {
+            return Ok(conn.clone());
+        }
+
+        let manager = ConnectionManager::new(self.client.clone()).await?;
+        *conn = Some(manager.clone());
+        Ok(manager)
+    }
+
+    async fn run<T, F>(&self, call: impl FnOnce(ConnectionManager) -> F) -> RedisResult<T>
+    where
+        F: Future<Output = RedisResult<T>>,
+    {
+        if !self.breaker_allows() {
+            return Err(RedisError::from((ErrorKind::IoError, "Redis circuit is open")));
+        }
+
+        let result = match tokio::time::timeout(self.timeout, async {
+            let conn = self.connection().await?;
+            call(conn).await
+        })
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(timed_out()),
+        };
+
+        match &result {
+            Ok(_) => self.record_success(),
+            Err(e) => self.record_failure(e),
+        }
+        result
+    }
+
+    // Once open, a single call is let through after the backoff to probe Redis again
+    fn breaker_allows(&self) -> bool {
+        let mut breaker = self.breaker.lock().unwrap();
+        match breaker.open_until {
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                breaker.open_until = Some(Instant::now() + BREAKER_BACKOFF);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record_success(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        if breaker.open_until.is_some() {
+            info!("Redis is reachable again, resuming cache use");
+        }
+        *breaker = Breaker::default();
+    }
+
+    fn record_failure(&self, e: &RedisError) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.failures += 1;
+        if breaker.failures >= BREAKER_THRESHOLD && breaker.open_until.is_none() {
+            warn!(
+                "Redis failed {} times in a row ({}), serving from Postgres for {}s at a time",
+                breaker.failures,
+                e,
+                BREAKER_BACKOFF.as_secs()
+            );
+            breaker.open_until = Some(Instant::now() + BREAKER_BACKOFF);
+        }
+    }
+
+    // Value and remaining lifetime, answered from the local copy when Redis cannot be reached
+    async fn fetch(&self, key: &str) -> Option<(String, Duration)> {
+        let mut pipe = redis::pipe();
+        pipe.get(key).pttl(key);
+
+        let result: RedisResult<(Option<String>, i64)> =
+            self.run(|mut conn| async move { pipe.query_async(&mut conn).await }).await;
+
+        match result {
+            Ok((value, ttl)) => value.map(|v| (v, Duration::from_millis(ttl.max(0) as u64))),
+            Err(_) => self.local_get(key),
+        }
+    }
+
+    fn local_get(&self, key: &str) -> Option<(String, Duration)> {
+        let mut local = self.local.lock().unwrap();
+        let now = Instant::now();
+        match local.get(key) {
+            Some((value, expires)) if *expires > now => Some((value.clone(), *expires - now)),
+            Some(_) => {
+                local.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn local_put(&self, key: &str, value: String) {
+        let expires = Instant::now() + Duration::from_secs(CACHE_DURATION as u64);
+        self.local.lock().unwrap().put(key.to_string(), (value, expires));
+    }
+
+    fn local_remove(&self, keys: &[String]) {
+        let mut local = self.local.lock().unwrap();
+        for key in keys {
+            local.pop(key);
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    async fn cache_rating(&self, target_id: i64, average: f64, prefix: &str) -> RedisResult<()> {
+        let key = format!("{}:{}:rating", prefix, target_id);
+        self.local_put(&key, average.to_string());
+        self.run(|mut conn| async move {
+            conn.set_ex::<_, _, ()>(key, average.to_string(), CACHE_DURATION).await
+        })
+        .await?;
+        info!("Cached {} rating for ID {}: {}", prefix, target_id, average);
+        Ok(())
+    }
+
+    async fn get_rating(&self, target_id: i64, prefix: &str) -> RedisResult<Option<Cached<f64>>> {
+        let key = format!("{}:{}:rating", prefix, target_id);
+        let value = self.fetch(&key).await;
+        
+        match value {
+            Some((v, ttl)) => match v.parse() {
+                Ok(rating) => {
+                    info!("Cache hit for {} rating ID {}", prefix, target_id);
+                    Ok(Some(Cached { value: rating, ttl }))
+                },
+                Err(e) => {
+                    error!("Failed to parse cached rating: {}", e);
+                    Ok(None)
+                }
+            },
+            None => {
+                info!("Cache miss for {} rating ID {}", prefix, target_id);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn cache_count(&self, target_id: i64, count: i64, prefix: &str) -> RedisResult<()> {
+        let key = format!("{}:{}:count", prefix, target_id);
+        self.local_put(&key, count.to_string());
+        self.run(|mut conn| async move {
+            conn.set_ex::<_, _, ()>(key, count.to_string(), CACHE_DURATION).await
+        })
+        .await?;
+        info!("Cached {} count for ID {}: {}", prefix, target_id, count);
+        Ok(())
+    }
+
+    async fn get_count(&self, target_id: i64, prefix: &str) -> RedisResult<Option<Cached<i64>>> {
+        let key = format!("{}:{}:count", prefix, target_id);
+        let value = self.fetch(&key).await;
+        
+        match value {
+            Some((v, ttl)) => match v.parse() {
+                Ok(count) => {
+                    info!("Cache hit for {} count ID {}", prefix, target_id);
+                    Ok(Some(Cached { value: count, ttl }))
+                },
+                Err(e) => {
+                    error!("Failed to parse cached count: {}", e);
+                    Ok(None)
+                }
+            },
+            None => {
+                info!("Cache miss for {} count ID {}", prefix, target_id);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn invalidate_many(&self, targets: &[(i64, &str)]) -> RedisResult<()> {
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for (target_id, prefix) in targets {
+            let keys = [
+                format!("{}:{}:rating", prefix, target_id),
+                format!("{}:{}:count", prefix, target_id),
+            ];
+            self.local_remove(&keys);
+            pipe.del(&keys).ignore();
+        }
+
+        self.run(|mut conn| async move { pipe.query_async::<_, ()>(&mut conn).await }).await
+    }
+
+    // Fixed window counter, the window starts with the first hit
+    async fn hit_window(&self, key: &str, window: usize) -> RedisResult<(i64, i64)> {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("SET").arg(key).arg(0).arg("EX").arg(window).arg("NX").ignore()
+            .incr(key, 1)
+            .ttl(key);
+
+        self.run(|mut conn| async move { pipe.query_async(&mut conn).await }).await
+    }
+
+    async fn release_window(&self, key: &str) -> RedisResult<()> {
+        // A plain DECR would recreate an expired window as a counter that never expires
+        let script = redis::Script::new(
+            "if redis.call('EXISTS', KEYS[1]) == 1 then redis.call('DECR', KEYS[1]) end",
+        );
+        self.run(|mut conn| async move { script.key(key).invoke_async::<_, ()>(&mut conn).await }).await
+    }
+
+    async fn set_cooldown(&self, key: &str, seconds: usize) -> RedisResult<()> {
+        self.run(|mut conn| async move { conn.set_ex::<_, _, ()>(key, 1, seconds).await }).await
+    }
+
+    async fn claim_cooldown(&self, key: &str, seconds: usize) -> RedisResult<Option<i64>> {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("SET").arg(key).arg(1).arg("EX").arg(seconds).arg("NX")
+            .ttl(key);
+
+        // SET NX answers nil when a cooldown was already running
+        let (claimed, ttl): (Option<String>, i64) =
+            self.run(|mut conn| async move { pipe.query_async(&mut conn).await }).await?;
+        Ok(if claimed.is_none() && ttl > 0 { Some(ttl) } else { None })
+    }
+}
+
+fn timed_out() -> RedisError {
+    RedisError::from((ErrorKind::IoError, "Redis call timed out"))
+}
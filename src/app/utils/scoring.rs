@@ -0,0 +1,237 @@
+use std::env;
+use time::{Duration, OffsetDateTime};
+use crate::app::{
+    models::review::{RatedTarget, ReviewType, ScoreView},
+    utils::store::ReviewStore,
+};
+
+const DEFAULT_PRIOR_WEIGHT: f64 = 5.0;
+const DEFAULT_HALF_LIFE_DAYS: i64 = 180;
+const RECENT_DAYS: i64 = 90;
+// 95% confidence
+const WILSON_Z: f64 = 1.96;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoringMode {
+    // The plain average, how ratings were ranked before scoring existed
+    Mean,
+    // Pulls the average towards the average of all targets until enough reviews are in
+    Bayesian,
+    // Lower bound of the Wilson interval, so a few reviews can only rank so high
+    Wilson,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreConfig {
+    pub mode: ScoringMode,
+    pub prior_weight: f64,
+    pub half_life: Duration,
+}
+
+impl ScoreConfig {
+    pub fn from_env() -> Self {
+        let mode = match env::var("RATING_SCORE_MODE").as_deref() {
+            Ok("mean") => ScoringMode::Mean,
+            Ok("wilson") => ScoringMode::Wilson,
+            _ => ScoringMode::Bayesian,
+        };
+        let prior_weight = env::var("RATING_PRIOR_WEIGHT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_PRIOR_WEIGHT);
+        let half_life_days = env::var("RATING_HALF_LIFE_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|days: &i64| *days > 0)
+            .unwrap_or(DEFAULT_HALF_LIFE_DAYS);
+
+        Self { mode, prior_weight, half_life: Duration::days(half_life_days) }
+    }
+
+    /// Score on the same 1-5 scale as ratings, 0 when there are no reviews.
+    /// `weight` is the review count, or the summed age weights in the decayed view,
+    /// and `prior` is the average over every target of the same type.
+    pub fn score(&self, average: f64, weight: f64, prior: Option<f64>) -> f64 {
+        if weight <= 0.0 {
+            return 0.0;
+        }
+        let n = weight;
+
+        match self.mode {
+            ScoringMode::Mean => average,
+            ScoringMode::Bayesian => {
+                let prior = prior.unwrap_or(3.0);
+                (self.prior_weight * prior + n * average) / (self.prior_weight + n)
+            }
+            ScoringMode::Wilson => {
+                // Stars mapped onto a 0-1 "positive" share
+                let p = ((average - 1.0) / 4.0).clamp(0.0, 1.0);
+                let z2 = WILSON_Z * WILSON_Z;
+                let lower = (p + z2 / (2.0 * n)
+                    - WILSON_Z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt())
+                    / (1.0 + z2 / n);
+                1.0 + 4.0 * lower.max(0.0)
+            }
+        }
+    }
+
+    pub fn view_label(&self, view: ScoreView) -> String {
+        match view {
+            ScoreView::AllTime => "all time".to_string(),
+            ScoreView::Recent => format!("last {} days", RECENT_DAYS),
+            ScoreView::Decayed => format!("half-life {} days", self.half_life.whole_days()),
+        }
+    }
+
+    // Score of one target in the given view, `average` and `count` are its all-time totals.
+    // Only looks up the prior when the mode needs it
+    pub async fn target_score<S: ReviewStore>(
+        &self,
+        db: &S,
+        target_id: i64,
+        review_type: &ReviewType,
+        view: ScoreView,
+        average: f64,
+        count: i64,
+    ) -> Result<f64, sqlx::Error> {
+        let (average, weight) = match view {
+            ScoreView::AllTime => (average, count as f64),
+            ScoreView::Recent => {
+                let since = OffsetDateTime::now_utc() - Duration::days(RECENT_DAYS);
+                let rating = db.get_weighted_rating(target_id, review_type, Some(since), None).await?;
+                (rating.average.unwrap_or(0.0), rating.weight)
+            }
+            ScoreView::Decayed => {
+                let rating = db.get_weighted_rating(target_id, review_type, None, Some(self.half_life)).await?;
+                (rating.average.unwrap_or(0.0), rating.weight)
+            }
+        };
+
+        let prior = match self.mode {
+            ScoringMode::Bayesian if weight > 0.0 => db.get_global_average(review_type).await?,
+            _ => None,
+        };
+
+        Ok(self.score(average, weight, prior))
+    }
+
+    // Highest all-time scores first, ties go to the target with more reviews
+    pub async fn leaderboard<S: ReviewStore>(
+        &self,
+        db: &S,
+        review_type: &ReviewType,
+        limit: usize,
+    ) -> Result<Vec<(RatedTarget, f64)>, sqlx::Error> {
+        let prior = match self.mode {
+            ScoringMode::Bayesian => db.get_global_average(review_type).await?,
+            _ => None,
+        };
+
+        let mut ranked: Vec<(RatedTarget, f64)> = db
+            .get_rated_targets(review_type)
+            .await?
+            .into_iter()
+            .map(|target| {
+                let score = self.score(target.average, target.review_count as f64, prior);
+                (target, score)
+            })
+            .collect();
+
+        ranked.sort_by(|(a, a_score), (b, b_score)| {
+            b_score.total_cmp(a_score).then(b.review_count.cmp(&a.review_count))
+        });
+        ranked.truncate(limit);
+
+        Ok(ranked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::app::{models::review::ReviewStatus, utils::memory_store::MemoryStore};
+
+    const MODES: [ScoringMode; 3] = [ScoringMode::Mean, ScoringMode::Bayesian, ScoringMode::Wilson];
+
+    fn config(mode: ScoringMode, prior_weight: f64) -> ScoreConfig {
+        ScoreConfig { mode, prior_weight, half_life: Duration::days(DEFAULT_HALF_LIFE_DAYS) }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn no_reviews_score_zero() {
+        for mode in MODES {
+            assert_eq!(config(mode, 5.0).score(4.0, 0.0, Some(3.0)), 0.0);
+        }
+    }
+
+    #[test]
+    fn a_single_review_is_held_back() {
+        assert_eq!(config(ScoringMode::Mean, 5.0).score(5.0, 1.0, Some(3.0)), 5.0);
+        assert!(close(config(ScoringMode::Bayesian, 5.0).score(5.0, 1.0, Some(3.0)), 20.0 / 6.0));
+        // Without a prior the midpoint of the scale stands in
+        assert!(close(config(ScoringMode::Bayesian, 5.0).score(5.0, 1.0, None), 20.0 / 6.0));
+
+        let wilson = config(ScoringMode::Wilson, 5.0).score(5.0, 1.0, None);
+        assert!(wilson > 1.0 && wilson < 3.0);
+    }
+
+    #[test]
+    fn no_prior_weight_is_the_plain_average() {
+        let bayesian = config(ScoringMode::Bayesian, 0.0);
+
+        assert!(close(bayesian.score(4.2, 1.0, Some(1.0)), 4.2));
+        assert!(close(bayesian.score(4.2, 30.0, Some(1.0)), 4.2));
+    }
+
+    #[test]
+    fn unanimous_ratings_stay_on_the_scale() {
+        for mode in MODES {
+            let config = config(mode, 5.0);
+            for n in [1.0, 10.0, 1000.0] {
+                let top = config.score(5.0, n, Some(3.0));
+                let bottom = config.score(1.0, n, Some(3.0));
+                assert!((1.0..=5.0).contains(&top) && (1.0..=5.0).contains(&bottom));
+                assert!(bottom < top);
+            }
+        }
+
+        // More unanimous reviews move the score further towards their rating
+        let wilson = config(ScoringMode::Wilson, 5.0);
+        assert!(wilson.score(5.0, 10.0, None) < wilson.score(5.0, 1000.0, None));
+        assert!(close(wilson.score(1.0, 10.0, None), 1.0));
+        let bayesian = config(ScoringMode::Bayesian, 5.0);
+        assert!(bayesian.score(1.0, 10.0, Some(3.0)) > bayesian.score(1.0, 1000.0, Some(3.0)));
+    }
+
+    #[tokio::test]
+    async fn decay_discounts_old_reviews() {
+        let db = MemoryStore::default();
+        let two_years_ago = OffsetDateTime::now_utc() - Duration::days(730);
+        for reviewer_id in 1..=4 {
+            let (review, _) = db
+                .upsert_review(10, reviewer_id, 5, None, BTreeMap::new(), &ReviewType::User, ReviewStatus::Published)
+                .await
+                .unwrap();
+            db.backdate(review.id, two_years_ago);
+            db.upsert_review(11, reviewer_id, 1, None, BTreeMap::new(), &ReviewType::User, ReviewStatus::Published)
+                .await
+                .unwrap();
+        }
+
+        for mode in MODES {
+            let config = config(mode, 5.0);
+            let score = |view| config.target_score(&db, 10, &ReviewType::User, view, 5.0, 4);
+            let all_time = score(ScoreView::AllTime).await.unwrap();
+            let decayed = score(ScoreView::Decayed).await.unwrap();
+            assert!(decayed <= all_time + 1e-9, "{:?}: {} > {}", mode, decayed, all_time);
+            if mode != ScoringMode::Mean {
+                assert!(decayed < all_time);
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+// Lets only one caller per key run at a time, the others wait for it to finish
+#[derive(Clone, Default)]
+pub struct SingleFlight {
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl SingleFlight {
+    pub async fn run<T, F, Fut>(&self, key: &str, call: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+
+        let result = {
+            let _guard = lock.lock().await;
+            call().await
+        };
+
+        // Only the map and this caller still hold the lock, nobody is waiting on it
+        let mut locks = self.locks.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(key);
+        }
+
+        result
+    }
+}
@@ -0,0 +1,320 @@
+use std::{collections::BTreeMap, future::Future};
+use time::{Duration, OffsetDateTime};
+use crate::app::models::{
+    api_key::ApiKey,
+    review::{
+        Review, ReviewRevision, ReviewStatus, ReviewType, ReviewReport, ReportResolution,
+        BlacklistEntry, BlacklistScope, TargetFlag, TargetActivity, RatingDistribution,
+        RatedTarget, ScoreView, WeightedRating, RatingCriterion, ReviewQuery, VoteTally,
+    },
+};
+
+pub trait ReviewStore: Clone + Send + Sync + 'static {
+    /// Inserts the review, or replaces the reviewer's existing one for this target,
+    /// in a single statement. The flag is `true` when a new row was created.
+    /// `status` is the moderation outcome; a review hidden by moderators stays hidden.
+    #[allow(clippy::too_many_arguments)]
+    fn upsert_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        rating: i32,
+        comment: Option<String>,
+        criteria: BTreeMap<String, i32>,
+        review_type: &ReviewType,
+        status: ReviewStatus,
+    ) -> impl Future<Output = Result<(Review, bool), sqlx::Error>> + Send;
+
+    fn has_reviewed(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send;
+
+    fn get_paginated_reviews(
+        &self,
+        target_id: i64,
+        page: i64,
+        per_page: i64,
+        review_type: &ReviewType,
+        query: &ReviewQuery,
+    ) -> impl Future<Output = Result<Vec<Review>, sqlx::Error>> + Send;
+
+    /// Counts the visible reviews `get_paginated_reviews` pages through for the same query.
+    fn count_matching_reviews(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        query: &ReviewQuery,
+    ) -> impl Future<Output = Result<i64, sqlx::Error>> + Send;
+
+    fn get_average_rating(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<Option<f64>, sqlx::Error>> + Send;
+
+    fn get_rating_distribution(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<RatingDistribution, sqlx::Error>> + Send;
+
+    fn get_reviews_count(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<i64, sqlx::Error>> + Send;
+
+    /// Looks up a reviewer's review, including ones pending or hidden by moderators.
+    fn get_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<Option<Review>, sqlx::Error>> + Send;
+
+    /// Earlier versions of a review, most recently replaced first.
+    fn get_review_revisions(
+        &self,
+        review_id: i32,
+    ) -> impl Future<Output = Result<Vec<ReviewRevision>, sqlx::Error>> + Send;
+
+    /// Soft-deletes a live review, recording who removed it and why.
+    /// Returns `false` when there was nothing to delete.
+    fn delete_review(
+        &self,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+        deleted_by: i64,
+        reason: Option<String>,
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send;
+
+    /// Looks a review up by id, including ones hidden by moderators.
+    fn get_review_by_id(
+        &self,
+        review_id: i32,
+    ) -> impl Future<Output = Result<Option<Review>, sqlx::Error>> + Send;
+
+    /// The moderation status of a live review, `None` once it is deleted.
+    fn get_review_status(
+        &self,
+        review_id: i32,
+    ) -> impl Future<Output = Result<Option<ReviewStatus>, sqlx::Error>> + Send;
+
+    /// Files a report against a published review. Returns `None` when the review
+    /// is gone or the reporter already reported it.
+    fn report_review(
+        &self,
+        review_id: i32,
+        reporter_id: i64,
+        reason: String,
+    ) -> impl Future<Output = Result<Option<ReviewReport>, sqlx::Error>> + Send;
+
+    /// Closes an open report and applies the moderator's decision to the review.
+    /// Returns `None` when the report does not exist or was already resolved.
+    fn resolve_report(
+        &self,
+        report_id: i32,
+        moderator_id: i64,
+        resolution: ReportResolution,
+    ) -> impl Future<Output = Result<Option<ReviewReport>, sqlx::Error>> + Send;
+
+    /// Publishes or rejects a review held by the moderation pipeline.
+    /// Returns `None` when the review is no longer pending.
+    fn resolve_pending_review(
+        &self,
+        review_id: i32,
+        moderator_id: i64,
+        approve: bool,
+    ) -> impl Future<Output = Result<Option<Review>, sqlx::Error>> + Send;
+
+    /// Adds or replaces the user's entry for this scope.
+    fn blacklist_user(
+        &self,
+        user_id: i64,
+        scope: BlacklistScope,
+        reason: Option<String>,
+        expires_at: Option<OffsetDateTime>,
+        created_by: i64,
+    ) -> impl Future<Output = Result<BlacklistEntry, sqlx::Error>> + Send;
+
+    fn unblacklist_user(
+        &self,
+        user_id: i64,
+        scope: BlacklistScope,
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send;
+
+    /// Deletes entries that have expired and recounts the targets of everyone they hid.
+    /// Returns how many entries were deleted.
+    fn purge_expired_blacklist(&self) -> impl Future<Output = Result<u64, sqlx::Error>> + Send;
+
+    /// Entries for the user that have not expired yet.
+    fn get_active_blacklist(
+        &self,
+        user_id: i64,
+    ) -> impl Future<Output = Result<Vec<BlacklistEntry>, sqlx::Error>> + Send;
+
+    /// Targets that received at least `min_reviews` published reviews since `since`.
+    fn get_review_activity(
+        &self,
+        since: OffsetDateTime,
+        min_reviews: i64,
+    ) -> impl Future<Output = Result<Vec<TargetActivity>, sqlx::Error>> + Send;
+
+    /// Returns `None` when the target is already flagged, so callers only alert once.
+    /// A flag cleared by a moderator is only replaced by bursts that started after it.
+    fn flag_target(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        reason: String,
+        frozen_average: Option<f64>,
+        since: OffsetDateTime,
+        expires_at: OffsetDateTime,
+    ) -> impl Future<Output = Result<Option<TargetFlag>, sqlx::Error>> + Send;
+
+    fn get_target_flag(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<Option<TargetFlag>, sqlx::Error>> + Send;
+
+    fn clear_target_flag(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        moderator_id: i64,
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send;
+
+    /// Average over every published review of this type, the prior for Bayesian scores.
+    fn get_global_average(
+        &self,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<Option<f64>, sqlx::Error>> + Send;
+
+    /// Every reviewed target of this type that is not currently flagged, for ranking.
+    fn get_rated_targets(
+        &self,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<Vec<RatedTarget>, sqlx::Error>> + Send;
+
+    /// Published reviews since `since` (all of them when `None`), each weighted by
+    /// 0.5^(age / half_life) when a half-life is given.
+    fn get_weighted_rating(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        since: Option<OffsetDateTime>,
+        half_life: Option<Duration>,
+    ) -> impl Future<Output = Result<WeightedRating, sqlx::Error>> + Send;
+
+    fn get_score_view(
+        &self,
+        guild_id: i64,
+    ) -> impl Future<Output = Result<Option<ScoreView>, sqlx::Error>> + Send;
+
+    fn set_score_view(
+        &self,
+        guild_id: i64,
+        view: ScoreView,
+        updated_by: i64,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    /// The guild's own criteria for this review type, or the defaults when it has none.
+    fn get_criteria(
+        &self,
+        review_type: &ReviewType,
+        guild_id: Option<i64>,
+    ) -> impl Future<Output = Result<Vec<RatingCriterion>, sqlx::Error>> + Send;
+
+    /// Replaces the guild's criteria, an empty list puts it back on the defaults.
+    fn set_guild_criteria(
+        &self,
+        review_type: &ReviewType,
+        guild_id: i64,
+        criteria: Vec<RatingCriterion>,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    /// Sub-averages of the given criteria, criteria nobody rated yet are left out.
+    fn get_criteria_averages(
+        &self,
+        target_id: i64,
+        review_type: &ReviewType,
+        keys: &[String],
+    ) -> impl Future<Output = Result<BTreeMap<String, f64>, sqlx::Error>> + Send;
+
+    /// Records a helpful or not helpful vote, one per user per review; repeating a vote
+    /// withdraws it. Returns `None` when the review no longer exists.
+    fn vote_review(
+        &self,
+        review_id: i32,
+        voter_id: i64,
+        helpful: bool,
+    ) -> impl Future<Output = Result<Option<VoteTally>, sqlx::Error>> + Send;
+
+    /// Sets the user's reaction, replacing any earlier one. `false` when the review is gone.
+    fn react_to_review(
+        &self,
+        review_id: i32,
+        user_id: i64,
+        emoji: String,
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send;
+
+    /// Each emoji with how many users picked it, most picked first.
+    fn get_review_reactions(
+        &self,
+        review_id: i32,
+    ) -> impl Future<Output = Result<Vec<(String, i64)>, sqlx::Error>> + Send;
+
+    /// Sets the reviewed party's answer, `None` removes it. Returns `None` when the review is gone.
+    fn reply_to_review(
+        &self,
+        review_id: i32,
+        author_id: i64,
+        reply: Option<String>,
+    ) -> impl Future<Output = Result<Option<Review>, sqlx::Error>> + Send;
+
+    fn get_reply_role(
+        &self,
+        guild_id: i64,
+    ) -> impl Future<Output = Result<Option<i64>, sqlx::Error>> + Send;
+
+    fn set_reply_role(
+        &self,
+        guild_id: i64,
+        role_id: Option<i64>,
+        updated_by: i64,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+
+    fn create_api_key(
+        &self,
+        name: String,
+        key_prefix: String,
+        key_hash: String,
+        scopes: Vec<String>,
+        created_by: i64,
+    ) -> impl Future<Output = Result<ApiKey, sqlx::Error>> + Send;
+
+    fn revoke_api_key(&self, key_id: i32) -> impl Future<Output = Result<bool, sqlx::Error>> + Send;
+
+    fn list_api_keys(&self) -> impl Future<Output = Result<Vec<ApiKey>, sqlx::Error>> + Send;
+
+    /// Looks up an unrevoked key by the hash of its secret and marks it as used.
+    fn authenticate_api_key(
+        &self,
+        key_hash: &str,
+    ) -> impl Future<Output = Result<Option<ApiKey>, sqlx::Error>> + Send;
+
+    fn record_api_write(
+        &self,
+        api_key_id: i32,
+        action: &str,
+        review_id: Option<i32>,
+        target_id: i64,
+        reviewer_id: i64,
+        review_type: &ReviewType,
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+}
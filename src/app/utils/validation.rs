@@ -0,0 +1,236 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use unicode_normalization::UnicodeNormalization;
+use crate::app::models::review::{RatingCriterion, ReviewType};
+
+pub const MIN_RATING: i32 = 1;
+pub const MAX_RATING: i32 = 5;
+pub const MIN_COMMENT_LENGTH: usize = 3;
+pub const MAX_COMMENT_LENGTH: usize = 2000;
+
+const ZERO_WIDTH: [char; 6] = ['\u{200B}', '\u{200C}', '\u{2060}', '\u{FEFF}', '\u{180E}', '\u{00AD}'];
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+}
+
+// A review as submitted, before anything about it is trusted
+pub struct ReviewDraft<'a> {
+    pub review_type: &'a str,
+    pub target_id: i64,
+    pub reviewer_id: i64,
+    // None when the rating is missing or not a number
+    pub rating: Option<i32>,
+    pub comment: Option<&'a str>,
+    pub criteria: BTreeMap<String, Option<i32>>,
+}
+
+#[derive(Debug)]
+pub struct ValidReview {
+    pub review_type: ReviewType,
+    pub rating: i32,
+    pub comment: Option<String>,
+    pub criteria: BTreeMap<String, i32>,
+}
+
+// Compatibility forms are folded so look-alike letters cannot slip past the word lists.
+// Joiners inside emoji sequences are kept, every other invisible character is dropped
+pub fn clean_comment(comment: &str) -> Option<String> {
+    let is_symbol = |c: Option<char>| c.is_some_and(|c| !c.is_ascii() && !c.is_alphanumeric());
+    let normalized: Vec<char> = comment.nfkc().collect();
+    let mut cleaned = String::with_capacity(normalized.len());
+
+    for (i, &c) in normalized.iter().enumerate() {
+        if ZERO_WIDTH.contains(&c) {
+            continue;
+        }
+        if c == ZERO_WIDTH_JOINER
+            && !(is_symbol(cleaned.chars().next_back()) && is_symbol(normalized.get(i + 1).copied()))
+        {
+            continue;
+        }
+        cleaned.push(c);
+    }
+
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() {
+        None
+    } else {
+        Some(cleaned.to_string())
+    }
+}
+
+/// Checks every field and reports all problems at once instead of stopping at the first.
+pub fn validate_review(
+    draft: ReviewDraft<'_>,
+    definitions: &[RatingCriterion],
+) -> Result<ValidReview, ValidationErrors> {
+    let mut errors = ValidationErrors::default();
+
+    let review_type = ReviewType::parse(draft.review_type);
+    if review_type.is_none() {
+        errors.add(
+            "review_type",
+            format!("Unknown review type '{}', expected user or server", draft.review_type),
+        );
+    }
+
+    if review_type == Some(ReviewType::User) && draft.target_id == draft.reviewer_id {
+        errors.add("target_id", "You cannot review yourself");
+    }
+
+    let rating = draft.rating.filter(|r| (MIN_RATING..=MAX_RATING).contains(r));
+    if rating.is_none() {
+        errors.add(
+            "rating",
+            format!("Rating must be a whole number between {} and {}", MIN_RATING, MAX_RATING),
+        );
+    }
+
+    let comment = draft.comment.and_then(clean_comment);
+    if let Some(comment) = &comment {
+        let length = comment.chars().count();
+        if length < MIN_COMMENT_LENGTH {
+            errors.add(
+                "comment",
+                format!("Comment must be at least {} characters long", MIN_COMMENT_LENGTH),
+            );
+        } else if length > MAX_COMMENT_LENGTH {
+            errors.add(
+                "comment",
+                format!("Comment must be at most {} characters long, it has {}", MAX_COMMENT_LENGTH, length),
+            );
+        }
+    }
+
+    let mut criteria = BTreeMap::new();
+    for (key, value) in draft.criteria {
+        let field = format!("criteria.{}", key);
+        match (definitions.iter().find(|c| c.key == key), value) {
+            (None, _) => errors.add(field, format!("Unknown criterion '{}'", key)),
+            (Some(_), Some(value)) if (MIN_RATING..=MAX_RATING).contains(&value) => {
+                criteria.insert(key, value);
+            }
+            (Some(criterion), _) => errors.add(
+                field,
+                format!("{} must be a whole number between {} and {}", criterion.label, MIN_RATING, MAX_RATING),
+            ),
+        }
+    }
+
+    match (review_type, rating) {
+        (Some(review_type), Some(rating)) if errors.0.is_empty() => Ok(ValidReview {
+            review_type,
+            rating,
+            comment,
+            criteria,
+        }),
+        _ => Err(errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draft(rating: Option<i32>, comment: Option<&str>) -> ReviewDraft<'_> {
+        ReviewDraft {
+            review_type: "user",
+            target_id: 1,
+            reviewer_id: 2,
+            rating,
+            comment,
+            criteria: BTreeMap::new(),
+        }
+    }
+
+    fn fields(errors: &ValidationErrors) -> Vec<&str> {
+        errors.0.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn folds_compatibility_forms() {
+        assert_eq!(clean_comment("ｓｃａｍ").as_deref(), Some("scam"));
+        assert_eq!(clean_comment("ﬁne ①").as_deref(), Some("fine 1"));
+    }
+
+    #[test]
+    fn strips_zero_width_characters() {
+        assert_eq!(clean_comment("s\u{200B}c\u{00AD}a\u{FEFF}m").as_deref(), Some("scam"));
+        assert_eq!(clean_comment("s\u{200D}cam").as_deref(), Some("scam"));
+        assert_eq!(clean_comment(" \u{200B}\u{2060} "), None);
+    }
+
+    #[test]
+    fn keeps_joiners_inside_emoji_sequences() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        assert_eq!(clean_comment(family).as_deref(), Some(family));
+    }
+
+    #[test]
+    fn accepts_a_valid_review() {
+        let definitions = [RatingCriterion::from_label("Communication")];
+        let mut draft = draft(Some(4), Some("  Ｇreat\u{200B} trade  "));
+        draft.criteria.insert("communication".to_string(), Some(5));
+
+        let review = validate_review(draft, &definitions).unwrap();
+        assert_eq!(review.review_type, ReviewType::User);
+        assert_eq!(review.rating, 4);
+        assert_eq!(review.comment.as_deref(), Some("Great trade"));
+        assert_eq!(review.criteria, BTreeMap::from([("communication".to_string(), 5)]));
+    }
+
+    #[test]
+    fn collects_every_field_error() {
+        let definitions = [RatingCriterion::from_label("Communication")];
+        let mut draft = draft(Some(7), Some("ok"));
+        draft.review_type = "guild";
+        draft.criteria.insert("communication".to_string(), None);
+        draft.criteria.insert("speed".to_string(), Some(3));
+
+        let errors = validate_review(draft, &definitions).unwrap_err();
+        assert_eq!(
+            fields(&errors),
+            ["review_type", "rating", "comment", "criteria.communication", "criteria.speed"]
+        );
+    }
+
+    #[test]
+    fn rejects_self_reviews_and_missing_ratings() {
+        let mut draft = draft(None, None);
+        draft.target_id = draft.reviewer_id;
+
+        let errors = validate_review(draft, &[]).unwrap_err();
+        assert_eq!(fields(&errors), ["target_id", "rating"]);
+    }
+
+    #[test]
+    fn measures_comments_after_cleaning() {
+        let padded = format!("{}{}", "a".repeat(MAX_COMMENT_LENGTH), "\u{200B}".repeat(10));
+        assert!(validate_review(draft(Some(3), Some(&padded)), &[]).is_ok());
+
+        let too_long = "a".repeat(MAX_COMMENT_LENGTH + 1);
+        let errors = validate_review(draft(Some(3), Some(&too_long)), &[]).unwrap_err();
+        assert_eq!(fields(&errors), ["comment"]);
+
+        let hidden = "a\u{200B}\u{200B}\u{200B}b";
+        let errors = validate_review(draft(Some(3), Some(hidden)), &[]).unwrap_err();
+        assert_eq!(fields(&errors), ["comment"]);
+    }
+}